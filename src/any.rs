@@ -0,0 +1,204 @@
+use crate::{try_malloc_array, AllocError, Malloced};
+use core::any::Any;
+
+/// An [`Any`] type that can be cloned through a trait object.
+///
+/// This is implemented for every `T: Any + Clone`. Erasing a value with
+/// [`Malloced::into_any_cloneable`] keeps its clone function in the vtable, so
+/// a heterogeneous collection of <code>[Malloced]<dyn CloneAny></code> can
+/// still be cloned.
+///
+/// This trait is sealed and has no methods of its own, so importing it does
+/// not add methods to cloneable types. Use the associated functions on
+/// <code>[Malloced]<dyn CloneAny></code> instead:
+///
+/// ```compile_fail
+/// use malloced::CloneAny;
+///
+/// let _ = 1u32.as_any();
+/// ```
+pub trait CloneAny: Any + private::Sealed {}
+
+impl<T: Any + Clone> CloneAny for T {}
+
+mod private {
+    use super::*;
+
+    /// The object-safe operations behind [`CloneAny`].
+    ///
+    /// This is public in a private module so that it can be a supertrait of
+    /// `CloneAny` while its methods stay unnameable outside this crate.
+    pub trait Sealed {
+        fn clone_malloced(&self) -> Result<Malloced<dyn CloneAny>, AllocError>;
+
+        fn as_any(&self) -> &dyn Any;
+
+        fn as_any_mut(&mut self) -> &mut dyn Any;
+    }
+
+    impl<T: Any + Clone> Sealed for T {
+        fn clone_malloced(&self) -> Result<Malloced<dyn CloneAny>, AllocError> {
+            let value = self.clone();
+            let buf = try_malloc_array::<T>(1)?;
+
+            unsafe {
+                buf.as_ptr().write(value);
+                Ok(Malloced::from_raw(buf.as_ptr() as *mut dyn CloneAny))
+            }
+        }
+
+        #[inline]
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        #[inline]
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+}
+
+use self::private::Sealed;
+
+impl<T: Any + Clone> Malloced<T> {
+    /// Erases the static type `T` while keeping the ability to clone it.
+    ///
+    /// Unlike [`Malloced::into_any`](#method.into_any), the result can be
+    /// cloned via [`Malloced::try_clone`](#method.try_clone) or [`Clone`].
+    ///
+    /// This is also available via
+    /// [`From`](https://doc.rust-lang.org/std/convert/trait.From.html).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::into_any_cloneable(this)` instead of
+    /// `this.into_any_cloneable()`.
+    #[inline]
+    pub fn into_any_cloneable(this: Self) -> Malloced<dyn CloneAny> {
+        unsafe { Malloced::from_raw(Malloced::into_raw(this) as *mut dyn CloneAny) }
+    }
+}
+
+impl Malloced<dyn CloneAny> {
+    /// Clones the value into a new `malloc`-ed allocation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `malloc` fails.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::try_clone(this)` instead of `this.try_clone()`.
+    #[inline]
+    pub fn try_clone(this: &Self) -> Result<Self, AllocError> {
+        Sealed::clone_malloced(&**this)
+    }
+
+    /// Returns `true` if the inner type is the same as `T`.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::is::<T>(this)` instead of `this.is::<T>()`.
+    #[inline]
+    pub fn is<T: Any>(this: &Self) -> bool {
+        Self::as_any(this).is::<T>()
+    }
+
+    /// Upcasts the inner value to `&dyn Any`.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_any(this)` instead of `this.as_any()`.
+    #[inline]
+    pub fn as_any(this: &Self) -> &dyn Any {
+        Sealed::as_any(&**this)
+    }
+
+    /// Upcasts the inner value to `&mut dyn Any`.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_any_mut(this)` instead of `this.as_any_mut()`.
+    #[inline]
+    pub fn as_any_mut(this: &mut Self) -> &mut dyn Any {
+        Sealed::as_any_mut(&mut **this)
+    }
+
+    /// Attempt to downcast the instance to a concrete type.
+    #[inline]
+    pub fn downcast<T: Any>(self) -> Result<Malloced<T>, Self> {
+        if Self::is::<T>(&self) {
+            let raw: *mut dyn CloneAny = Malloced::into_raw(self);
+            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl Clone for Malloced<dyn CloneAny> {
+    /// Clones the value into a new `malloc`-ed allocation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails.
+    #[inline]
+    fn clone(&self) -> Self {
+        match Malloced::try_clone(self) {
+            Ok(clone) => clone,
+            Err(error) => panic!("{}", error),
+        }
+    }
+}
+
+impl<T: Any + Clone> From<Malloced<T>> for Malloced<dyn CloneAny> {
+    #[inline]
+    fn from(m: Malloced<T>) -> Self {
+        Malloced::into_any_cloneable(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clone() {
+        let a: Malloced<dyn CloneAny> = Malloced::alloc_one(1u32).unwrap().into();
+        let b = Malloced::into_any_cloneable(Malloced::alloc_one([2u8; 3]).unwrap());
+
+        let a2 = Malloced::try_clone(&a).unwrap();
+        let b2 = b.clone();
+        assert_ne!(
+            Malloced::as_ptr(&a2) as *const u8,
+            Malloced::as_ptr(&a) as *const u8
+        );
+
+        assert!(Malloced::is::<u32>(&a2));
+        assert!(!Malloced::is::<u8>(&a2));
+        assert_eq!(Malloced::as_any(&a2).downcast_ref::<u32>(), Some(&1));
+        assert_eq!(*b2.downcast::<[u8; 3]>().ok().unwrap(), [2; 3]);
+        assert!(b.downcast::<u32>().is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn heterogeneous() {
+        use std::string::String;
+
+        let mut values: Vec<Malloced<dyn CloneAny>> = vec![
+            Malloced::alloc_one(String::from("one")).unwrap().into(),
+            Malloced::alloc_one(2i64).unwrap().into(),
+        ];
+        let copies = values.clone();
+
+        Malloced::as_any_mut(&mut values[0])
+            .downcast_mut::<String>()
+            .unwrap()
+            .push('!');
+
+        fn string(m: &Malloced<dyn CloneAny>) -> Option<&String> {
+            Malloced::as_any(m).downcast_ref()
+        }
+
+        assert_eq!(string(&values[0]).unwrap(), "one!");
+        assert_eq!(string(&copies[0]).unwrap(), "one");
+        assert_eq!(Malloced::as_any(&copies[1]).downcast_ref::<i64>(), Some(&2));
+    }
+}
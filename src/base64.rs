@@ -0,0 +1,315 @@
+use crate::Malloced;
+use core::{fmt, mem::MaybeUninit};
+
+#[cfg(feature = "std")]
+use std::{error::Error, string::String};
+
+/// The alphabet used by
+/// <code>[Malloced](crate::Malloced)::<[\[u8\]](prim@slice)>::[from_base64](crate::Malloced::from_base64)</code>
+/// and [`to_base64`](crate::Malloced::to_base64).
+///
+/// This requires the `base64` feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Base64Alphabet {
+    /// The standard alphabet from RFC 4648, using `+` and `/`.
+    Standard,
+    /// The URL and filename safe alphabet from RFC 4648, using `-` and `_`.
+    UrlSafe,
+}
+
+impl Base64Alphabet {
+    #[cfg(feature = "std")]
+    #[inline]
+    fn symbols(self) -> &'static [u8; 64] {
+        match self {
+            Base64Alphabet::Standard => {
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"
+            }
+            Base64Alphabet::UrlSafe => {
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_"
+            }
+        }
+    }
+
+    #[inline]
+    fn decode(self, symbol: u8) -> Option<u8> {
+        match symbol {
+            b'A'..=b'Z' => Some(symbol - b'A'),
+            b'a'..=b'z' => Some(symbol - b'a' + 26),
+            b'0'..=b'9' => Some(symbol - b'0' + 52),
+            b'+' if self == Base64Alphabet::Standard => Some(62),
+            b'/' if self == Base64Alphabet::Standard => Some(63),
+            b'-' if self == Base64Alphabet::UrlSafe => Some(62),
+            b'_' if self == Base64Alphabet::UrlSafe => Some(63),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned by
+/// <code>[Malloced](crate::Malloced)::<[\[u8\]](prim@slice)>::[from_base64](crate::Malloced::from_base64)</code>.
+///
+/// This requires the `base64` feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// A byte that is not in the alphabet was found.
+    InvalidByte {
+        /// The invalid byte.
+        byte: u8,
+        /// The index of the byte in the input.
+        index: usize,
+    },
+    /// The number of symbols, excluding padding, leaves a single dangling
+    /// symbol.
+    InvalidLength,
+    /// The last symbol has non-zero bits that do not belong to any byte.
+    InvalidLastSymbol {
+        /// The invalid symbol.
+        byte: u8,
+        /// The index of the symbol in the input.
+        index: usize,
+    },
+    /// The `=` padding is malformed.
+    InvalidPadding,
+}
+
+impl fmt::Display for DecodeError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::InvalidByte { byte, index } => {
+                write!(f, "invalid byte {:#04x} at position {}", byte, index)
+            }
+            DecodeError::InvalidLength => f.write_str("invalid input length"),
+            DecodeError::InvalidLastSymbol { byte, index } => {
+                write!(f, "invalid last symbol {:#04x} at position {}", byte, index)
+            }
+            DecodeError::InvalidPadding => f.write_str("invalid padding"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for DecodeError {}
+
+impl Malloced<[u8]> {
+    /// Decodes the base64 string `s` directly into a `malloc`-ed buffer.
+    ///
+    /// Trailing `=` padding is optional, but must be correct if present.
+    ///
+    /// This requires the `base64` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if `s` is not valid base64 in `alphabet`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use malloced::{Base64Alphabet, Malloced};
+    ///
+    /// let buf = Malloced::<[u8]>::from_base64("aGVsbG8=", Base64Alphabet::Standard).unwrap();
+    /// assert_eq!(&*buf, b"hello");
+    /// ```
+    pub fn from_base64(s: &str, alphabet: Base64Alphabet) -> Result<Self, DecodeError> {
+        let s = s.as_bytes();
+
+        let data = if s.last() == Some(&b'=') {
+            let padding = if s.len() >= 2 && s[s.len() - 2] == b'=' {
+                2
+            } else {
+                1
+            };
+            if s.len() % 4 != 0 {
+                return Err(DecodeError::InvalidPadding);
+            }
+            &s[..s.len() - padding]
+        } else {
+            s
+        };
+
+        let rem = data.len() % 4;
+        if rem == 1 {
+            return Err(DecodeError::InvalidLength);
+        }
+
+        let len = data.len() / 4 * 3 + rem.saturating_sub(1);
+        let mut buf = Malloced::<[u8]>::new_uninit_slice(len);
+
+        let decode = |index: usize| -> Result<u32, DecodeError> {
+            let byte = data[index];
+            match alphabet.decode(byte) {
+                Some(value) => Ok(value as u32),
+                None => Err(DecodeError::InvalidByte { byte, index }),
+            }
+        };
+
+        let mut out = buf.iter_mut();
+        let mut write = |byte: u32| {
+            if let Some(out) = out.next() {
+                *out = MaybeUninit::new(byte as u8);
+            }
+        };
+
+        for quad in 0..data.len() / 4 {
+            let i = quad * 4;
+            let bits =
+                decode(i)? << 18 | decode(i + 1)? << 12 | decode(i + 2)? << 6 | decode(i + 3)?;
+            write(bits >> 16);
+            write(bits >> 8);
+            write(bits);
+        }
+
+        let i = data.len() - rem;
+        match rem {
+            2 => {
+                let last = decode(i + 1)?;
+                if last & 0xf != 0 {
+                    return Err(DecodeError::InvalidLastSymbol {
+                        byte: data[i + 1],
+                        index: i + 1,
+                    });
+                }
+                write(decode(i)? << 2 | last >> 4);
+            }
+            3 => {
+                let last = decode(i + 2)?;
+                if last & 0x3 != 0 {
+                    return Err(DecodeError::InvalidLastSymbol {
+                        byte: data[i + 2],
+                        index: i + 2,
+                    });
+                }
+                let bits = decode(i)? << 10 | decode(i + 1)? << 4 | last >> 2;
+                write(bits >> 8);
+                write(bits);
+            }
+            _ => {}
+        }
+
+        Ok(unsafe { Malloced::assume_init(buf) })
+    }
+
+    /// Encodes the bytes as a base64 string in `alphabet`, with `=` padding.
+    ///
+    /// This requires the `base64` and `std` features.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::to_base64(this, alphabet)` instead of
+    /// `this.to_base64(alphabet)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use malloced::{Base64Alphabet, Malloced};
+    ///
+    /// let buf: Malloced<[u8]> = vec![0xfb, 0xff].into_iter().collect();
+    /// assert_eq!(Malloced::to_base64(&buf, Base64Alphabet::Standard), "+/8=");
+    /// assert_eq!(Malloced::to_base64(&buf, Base64Alphabet::UrlSafe), "-_8=");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_base64(this: &Self, alphabet: Base64Alphabet) -> String {
+        let symbols = alphabet.symbols();
+        let symbol = |bits: u32| symbols[(bits & 0x3f) as usize] as char;
+
+        let mut s = String::with_capacity((this.len() + 2) / 3 * 4);
+        for chunk in this.chunks(3) {
+            let bits = chunk
+                .iter()
+                .enumerate()
+                .fold(0, |bits, (i, &byte)| bits | (byte as u32) << (16 - i * 8));
+
+            s.push(symbol(bits >> 18));
+            s.push(symbol(bits >> 12));
+            if chunk.len() > 1 {
+                s.push(symbol(bits >> 6));
+            } else {
+                s.push('=');
+            }
+            if chunk.len() > 2 {
+                s.push(symbol(bits));
+            } else {
+                s.push('=');
+            }
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(s: &str) -> Result<Malloced<[u8]>, DecodeError> {
+        Malloced::<[u8]>::from_base64(s, Base64Alphabet::Standard)
+    }
+
+    #[test]
+    fn decode_valid() {
+        assert_eq!(&*decode("").unwrap(), b"");
+        assert_eq!(&*decode("Zg==").unwrap(), b"f");
+        assert_eq!(&*decode("Zm8=").unwrap(), b"fo");
+        assert_eq!(&*decode("Zm9v").unwrap(), b"foo");
+        assert_eq!(&*decode("Zm9vYg").unwrap(), b"foob");
+        assert_eq!(&*decode("Zm9vYmE").unwrap(), b"fooba");
+        assert_eq!(&*decode("Zm9vYmFy").unwrap(), b"foobar");
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(
+            decode("Zm9v-w=="),
+            Err(DecodeError::InvalidByte {
+                byte: b'-',
+                index: 4
+            })
+        );
+        assert_eq!(
+            decode("Z=9v"),
+            Err(DecodeError::InvalidByte {
+                byte: b'=',
+                index: 1
+            })
+        );
+        assert_eq!(decode("Zm9vY"), Err(DecodeError::InvalidLength));
+        assert_eq!(decode("Zm8"), Ok(Malloced::<[u8]>::alloc(b"fo").unwrap()));
+        assert_eq!(decode("Zm8=="), Err(DecodeError::InvalidPadding));
+        assert_eq!(
+            decode("Zh=="),
+            Err(DecodeError::InvalidLastSymbol {
+                byte: b'h',
+                index: 1
+            })
+        );
+    }
+
+    #[test]
+    fn alphabet() {
+        let standard = Malloced::<[u8]>::from_base64("+/8=", Base64Alphabet::Standard).unwrap();
+        let url_safe = Malloced::<[u8]>::from_base64("-_8=", Base64Alphabet::UrlSafe).unwrap();
+        assert_eq!(&*standard, &[0xfb, 0xff]);
+        assert_eq!(standard, url_safe);
+
+        assert!(Malloced::<[u8]>::from_base64("-_8=", Base64Alphabet::Standard).is_err());
+        assert!(Malloced::<[u8]>::from_base64("+/8=", Base64Alphabet::UrlSafe).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn round_trip() {
+        let bytes: std::vec::Vec<u8> = (0..=255).collect();
+
+        for len in 0..bytes.len() {
+            let buf = Malloced::<[u8]>::alloc(&bytes[..len]).unwrap();
+
+            for &alphabet in &[Base64Alphabet::Standard, Base64Alphabet::UrlSafe] {
+                let s = Malloced::to_base64(&buf, alphabet);
+                assert_eq!(Malloced::<[u8]>::from_base64(&s, alphabet).unwrap(), buf);
+            }
+        }
+    }
+}
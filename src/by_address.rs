@@ -0,0 +1,102 @@
+use crate::Malloced;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+
+/// Compares and hashes a [`Malloced`] by its allocation address rather than by
+/// its contents.
+///
+/// This enables using `malloc`-ed objects as identity keys in maps and sets,
+/// where two distinct allocations with equal contents are different keys.
+///
+/// Only the address is considered, so pointer metadata such as slice length is
+/// ignored.
+#[derive(Clone, Copy, Default)]
+#[repr(transparent)]
+pub struct ByAddress<P>(pub P);
+
+impl<T: ?Sized> ByAddress<Malloced<T>> {
+    #[inline]
+    fn addr(&self) -> usize {
+        Malloced::as_ptr(&self.0) as *const () as usize
+    }
+}
+
+impl<P> From<P> for ByAddress<P> {
+    #[inline]
+    fn from(pointer: P) -> Self {
+        ByAddress(pointer)
+    }
+}
+
+impl<P> Deref for ByAddress<P> {
+    type Target = P;
+
+    #[inline]
+    fn deref(&self) -> &P {
+        &self.0
+    }
+}
+
+impl<P> DerefMut for ByAddress<P> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut P {
+        &mut self.0
+    }
+}
+
+impl<P: fmt::Debug> fmt::Debug for ByAddress<P> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: ?Sized> PartialEq for ByAddress<Malloced<T>> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl<T: ?Sized> Eq for ByAddress<Malloced<T>> {}
+
+impl<T: ?Sized> Hash for ByAddress<Malloced<T>> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn hash_set() {
+        use std::collections::HashSet;
+
+        let a = ByAddress(Malloced::alloc_one(1u32).unwrap());
+        let b = ByAddress(Malloced::alloc_one(1u32).unwrap());
+
+        assert_eq!(*a.0, *b.0);
+        assert_ne!(a, b);
+
+        let mut set = HashSet::new();
+        assert!(set.insert(a));
+        assert!(set.insert(b));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn eq() {
+        let a = ByAddress(Malloced::<[u8]>::alloc(&[1, 2]).unwrap());
+        let b = ByAddress(Malloced::<[u8]>::alloc(&[1, 2]).unwrap());
+
+        assert_eq!(a, a);
+        assert_ne!(a, b);
+    }
+}
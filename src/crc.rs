@@ -0,0 +1,125 @@
+use crate::Malloced;
+
+/// The reversed CRC-32 (IEEE 802.3) polynomial `0x04C11DB7`.
+const CRC32_POLY: u32 = 0xedb8_8320;
+
+/// The reversed CRC-32C (Castagnoli) polynomial `0x1EDC6F41`.
+const CRC32C_POLY: u32 = 0x82f6_3b78;
+
+static CRC32_TABLE: [u32; 256] = make_table(CRC32_POLY);
+static CRC32C_TABLE: [u32; 256] = make_table(CRC32C_POLY);
+
+/// Builds the byte-wise lookup table for the reversed polynomial `poly`.
+const fn make_table(poly: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+#[inline]
+fn checksum(table: &[u32; 256], bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc = table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+impl Malloced<[u8]> {
+    /// Computes the CRC-32 checksum of the bytes, without copying them.
+    ///
+    /// This is the CRC-32 used by zlib, gzip, PNG, and Ethernet, with the
+    /// polynomial `0x04C11DB7` (IEEE 802.3) in reflected form.
+    ///
+    /// This requires the `crc` feature.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::crc32(this)` instead of `this.crc32()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use malloced::Malloced;
+    ///
+    /// let buf: Malloced<[u8]> = b"123456789".iter().copied().collect();
+    /// assert_eq!(Malloced::crc32(&buf), 0xcbf43926);
+    /// ```
+    #[inline]
+    pub fn crc32(this: &Self) -> u32 {
+        checksum(&CRC32_TABLE, this)
+    }
+
+    /// Computes the CRC-32C checksum of the bytes, without copying them.
+    ///
+    /// This is the CRC-32 variant used by iSCSI, ext4, and SSE 4.2's `crc32`
+    /// instruction, with the Castagnoli polynomial `0x1EDC6F41` in reflected
+    /// form.
+    ///
+    /// This requires the `crc` feature.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::crc32c(this)` instead of `this.crc32c()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use malloced::Malloced;
+    ///
+    /// let buf: Malloced<[u8]> = b"123456789".iter().copied().collect();
+    /// assert_eq!(Malloced::crc32c(&buf), 0xe3069283);
+    /// ```
+    #[inline]
+    pub fn crc32c(this: &Self) -> u32 {
+        checksum(&CRC32C_TABLE, this)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32() {
+        let cases: &[(&[u8], u32)] = &[
+            (b"", 0),
+            (b"a", 0xe8b7_be43),
+            (b"123456789", 0xcbf4_3926),
+            (b"The quick brown fox jumps over the lazy dog", 0x414f_a339),
+        ];
+
+        for &(bytes, expected) in cases {
+            let buf = Malloced::<[u8]>::alloc(bytes).unwrap();
+            assert_eq!(Malloced::crc32(&buf), expected, "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn crc32c() {
+        let cases: &[(&[u8], u32)] = &[
+            (b"", 0),
+            (b"a", 0xc1d0_4330),
+            (b"123456789", 0xe306_9283),
+            (&[0; 32], 0x8a91_36aa),
+            (&[0xff; 32], 0x62a8_ab43),
+        ];
+
+        for &(bytes, expected) in cases {
+            let buf = Malloced::<[u8]>::alloc(bytes).unwrap();
+            assert_eq!(Malloced::crc32c(&buf), expected, "{:?}", bytes);
+        }
+    }
+}
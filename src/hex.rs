@@ -0,0 +1,173 @@
+use crate::Malloced;
+use core::{fmt, mem::MaybeUninit};
+
+#[cfg(feature = "std")]
+use std::{error::Error, string::String};
+
+/// The error returned by
+/// <code>[Malloced](crate::Malloced)::<[\[u8\]](prim@slice)>::[from_hex](crate::Malloced::from_hex)</code>.
+///
+/// This requires the `hex` feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FromHexError {
+    /// A character that is not a hexadecimal digit was found.
+    InvalidHexCharacter {
+        /// The invalid character.
+        c: char,
+        /// The byte index of the character in the input.
+        index: usize,
+    },
+    /// The input has an odd number of characters.
+    OddLength,
+}
+
+impl fmt::Display for FromHexError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FromHexError::InvalidHexCharacter { c, index } => {
+                write!(f, "invalid character {:?} at position {}", c, index)
+            }
+            FromHexError::OddLength => f.write_str("odd number of digits"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for FromHexError {}
+
+impl Malloced<[u8]> {
+    /// Decodes the hexadecimal string `s` directly into a `malloc`-ed buffer.
+    ///
+    /// Both uppercase and lowercase digits are accepted.
+    ///
+    /// This requires the `hex` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FromHexError::OddLength`] if `s` has an odd length, or
+    /// [`FromHexError::InvalidHexCharacter`] for the first character that is
+    /// not a hexadecimal digit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use malloced::Malloced;
+    ///
+    /// let buf = Malloced::<[u8]>::from_hex("c0ffee").unwrap();
+    /// assert_eq!(&*buf, &[0xc0, 0xff, 0xee]);
+    /// ```
+    pub fn from_hex(s: &str) -> Result<Self, FromHexError> {
+        if s.len() % 2 != 0 {
+            return Err(FromHexError::OddLength);
+        }
+
+        let mut buf = Malloced::<[u8]>::new_uninit_slice(s.len() / 2);
+
+        for (i, out) in buf.iter_mut().enumerate() {
+            let hi = decode_digit(s, i * 2)?;
+            let lo = decode_digit(s, i * 2 + 1)?;
+            *out = MaybeUninit::new(hi << 4 | lo);
+        }
+
+        Ok(unsafe { Malloced::assume_init(buf) })
+    }
+
+    /// Encodes the bytes as a lowercase hexadecimal string.
+    ///
+    /// This requires the `hex` and `std` features.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::to_hex_string(this)` instead of `this.to_hex_string()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use malloced::Malloced;
+    ///
+    /// let buf = Malloced::<[u8]>::from_hex("C0FFEE").unwrap();
+    /// assert_eq!(Malloced::to_hex_string(&buf), "c0ffee");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_hex_string(this: &Self) -> String {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+        let mut s = String::with_capacity(this.len() * 2);
+        for &byte in this.iter() {
+            s.push(DIGITS[(byte >> 4) as usize] as char);
+            s.push(DIGITS[(byte & 0xf) as usize] as char);
+        }
+        s
+    }
+}
+
+/// Decodes the digit at byte `index` of `s`.
+///
+/// Digits are decoded in order, so every byte before `index` is ASCII and
+/// `index` is always on a character boundary.
+#[inline]
+fn decode_digit(s: &str, index: usize) -> Result<u8, FromHexError> {
+    let digit = s.as_bytes()[index];
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        _ => Err(FromHexError::InvalidHexCharacter {
+            c: s[index..].chars().next().unwrap_or_default(),
+            index,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        let buf = Malloced::<[u8]>::from_hex("00ff7fA0").unwrap();
+        assert_eq!(&*buf, &[0x00, 0xff, 0x7f, 0xa0]);
+
+        let buf = Malloced::<[u8]>::from_hex("").unwrap();
+        assert_eq!(&*buf, &[]);
+    }
+
+    #[test]
+    fn odd_length() {
+        assert_eq!(
+            Malloced::<[u8]>::from_hex("abc"),
+            Err(FromHexError::OddLength)
+        );
+    }
+
+    #[test]
+    fn invalid_character() {
+        assert_eq!(
+            Malloced::<[u8]>::from_hex("0g"),
+            Err(FromHexError::InvalidHexCharacter { c: 'g', index: 1 })
+        );
+        assert_eq!(
+            Malloced::<[u8]>::from_hex("00 1"),
+            Err(FromHexError::InvalidHexCharacter { c: ' ', index: 2 })
+        );
+        assert_eq!(
+            Malloced::<[u8]>::from_hex("0éa"),
+            Err(FromHexError::InvalidHexCharacter { c: 'é', index: 1 })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn round_trip() {
+        let bytes: [u8; 4] = [0xde, 0xad, 0xbe, 0xef];
+        let buf = Malloced::<[u8]>::alloc(&bytes).unwrap();
+        let s = Malloced::to_hex_string(&buf);
+        assert_eq!(s, "deadbeef");
+
+        assert_eq!(Malloced::<[u8]>::from_hex(&s).unwrap(), buf);
+    }
+}
@@ -1,11 +1,18 @@
 use crate::{sys, Malloced};
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
 use core::{
-    ffi::c_void,
+    any::Any,
+    ffi::{c_void, CStr},
     fmt,
-    hash::{Hash, Hasher},
+    hash::{BuildHasher, Hash, Hasher},
+    mem::ManuallyDrop,
+    ops::{Index, IndexMut},
     pin::Pin,
-    ptr,
+    ptr::{self, NonNull},
 };
+#[cfg(feature = "std")]
+use std::error::Error;
 
 unsafe impl<T: ?Sized + Send> Send for Malloced<T> {}
 unsafe impl<T: ?Sized + Sync> Sync for Malloced<T> {}
@@ -21,6 +28,22 @@ impl<T: ?Sized> Drop for Malloced<T> {
     }
 }
 
+impl<T: Clone> Clone for Malloced<[T]> {
+    /// Clones the elements into a new `malloc`-ed allocation of the same
+    /// length.
+    ///
+    /// See [`Malloced::clone_with_capacity`](struct.Malloced.html#method.clone_with_capacity)
+    /// to also match the source's spare capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails or does not return memory aligned for `T`.
+    #[inline]
+    fn clone(&self) -> Self {
+        Malloced::from_fn(self.len(), |i| self[i].clone())
+    }
+}
+
 impl<T: ?Sized> core::ops::Deref for Malloced<T> {
     type Target = T;
 
@@ -51,6 +74,21 @@ impl<T: ?Sized> AsMut<T> for Malloced<T> {
     }
 }
 
+impl AsRef<[u8]> for Malloced<str> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// The bytes exclude the trailing nul terminator.
+impl AsRef<[u8]> for Malloced<CStr> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.to_bytes()
+    }
+}
+
 impl<T: ?Sized> core::borrow::Borrow<T> for Malloced<T> {
     #[inline]
     fn borrow(&self) -> &T {
@@ -72,6 +110,145 @@ impl From<Malloced<str>> for Malloced<[u8]> {
     }
 }
 
+impl<T, const N: usize> From<Malloced<[T; N]>> for Malloced<[T]> {
+    #[inline]
+    fn from(m: Malloced<[T; N]>) -> Self {
+        Malloced::into_slice(m)
+    }
+}
+
+impl<T: ?Sized> From<Malloced<T>> for NonNull<T> {
+    #[inline]
+    fn from(m: Malloced<T>) -> Self {
+        ManuallyDrop::new(m).ptr
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, E: Error + 'a> From<Malloced<E>> for Malloced<dyn Error + 'a> {
+    #[inline]
+    fn from(m: Malloced<E>) -> Self {
+        unsafe { Self::from_raw(Malloced::into_raw(m) as *mut (dyn Error + 'a)) }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, E: Error + Send + Sync + 'a> From<Malloced<E>> for Malloced<dyn Error + Send + Sync + 'a> {
+    #[inline]
+    fn from(m: Malloced<E>) -> Self {
+        unsafe { Self::from_raw(Malloced::into_raw(m) as *mut (dyn Error + Send + Sync + 'a)) }
+    }
+}
+
+/// Erases the static type `T`, like [`Malloced::into_any`].
+impl<T: Any> From<Malloced<T>> for Malloced<dyn Any> {
+    #[inline]
+    fn from(m: Malloced<T>) -> Self {
+        Malloced::into_any(m)
+    }
+}
+
+/// Erases the static type `T`.
+impl<T: Any + Send> From<Malloced<T>> for Malloced<dyn Any + Send> {
+    #[inline]
+    fn from(m: Malloced<T>) -> Self {
+        unsafe { Self::from_raw(Malloced::into_raw(m) as *mut (dyn Any + Send)) }
+    }
+}
+
+/// Erases the static type `T`, like [`Malloced::into_any_send`].
+impl<T: Any + Send + Sync> From<Malloced<T>> for Malloced<dyn Any + Send + Sync> {
+    #[inline]
+    fn from(m: Malloced<T>) -> Self {
+        Malloced::into_any_send(m)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: ?Sized + ToOwned + 'a> From<Malloced<T>> for std::borrow::Cow<'a, T> {
+    #[inline]
+    fn from(m: Malloced<T>) -> Self {
+        Malloced::into_cow(m)
+    }
+}
+
+/// Moves the elements into a new `malloc`-ed allocation and frees the vector's
+/// buffer.
+///
+/// The elements are copied bitwise without being cloned or dropped, since
+/// memory from Rust's global allocator cannot be passed to `free`.
+///
+/// This requires the `alloc` feature.
+///
+/// # Panics
+///
+/// Panics if `malloc` fails, in which case `vec` is dropped.
+#[cfg(feature = "alloc")]
+impl<T> From<Vec<T>> for Malloced<[T]> {
+    fn from(mut vec: Vec<T>) -> Self {
+        let len = vec.len();
+        let buf = crate::malloc_array::<T>(len);
+
+        unsafe {
+            ptr::copy_nonoverlapping(vec.as_ptr(), buf.as_ptr(), len);
+
+            // Free the vector's buffer without dropping the moved elements.
+            vec.set_len(0);
+            drop(vec);
+
+            Malloced::slice_from_raw_parts(buf.as_ptr(), len)
+        }
+    }
+}
+
+/// Moves the elements into a new vector and frees the `malloc`-ed memory.
+///
+/// The elements are copied bitwise without being cloned or dropped.
+///
+/// This requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl<T> From<Malloced<[T]>> for Vec<T> {
+    fn from(m: Malloced<[T]>) -> Self {
+        let mut vec = Vec::with_capacity(m.len());
+        let (ptr, len) = Malloced::into_raw_parts(m);
+
+        unsafe {
+            ptr::copy_nonoverlapping(ptr, vec.as_mut_ptr(), len);
+            vec.set_len(len);
+            sys::free(ptr as *mut c_void);
+        }
+        vec
+    }
+}
+
+/// Copies the string into a new `malloc`-ed allocation.
+///
+/// This requires the `alloc` feature.
+///
+/// # Panics
+///
+/// Panics if `malloc` fails.
+#[cfg(feature = "alloc")]
+impl From<String> for Malloced<str> {
+    #[inline]
+    fn from(s: String) -> Self {
+        let bytes = Malloced::<[u8]>::from(s.into_bytes());
+        unsafe { Self::from_raw(Malloced::into_raw(bytes) as *mut str) }
+    }
+}
+
+/// Copies the string into a new `String` and frees the `malloc`-ed memory.
+///
+/// This requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+impl From<Malloced<str>> for String {
+    #[inline]
+    fn from(m: Malloced<str>) -> Self {
+        let bytes = Vec::from(Malloced::<[u8]>::from(m));
+        unsafe { String::from_utf8_unchecked(bytes) }
+    }
+}
+
 impl<T: ?Sized> From<Malloced<T>> for Pin<Malloced<T>> {
     #[inline]
     fn from(m: Malloced<T>) -> Self {
@@ -93,6 +270,26 @@ impl<T: ?Sized + fmt::Display> fmt::Display for Malloced<T> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<T: ?Sized + Error> Error for Malloced<T> {
+    #[inline]
+    #[allow(deprecated)]
+    fn description(&self) -> &str {
+        T::description(self)
+    }
+
+    #[inline]
+    #[allow(deprecated)]
+    fn cause(&self) -> Option<&dyn Error> {
+        T::cause(self)
+    }
+
+    #[inline]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        T::source(self)
+    }
+}
+
 impl<T: ?Sized> fmt::Pointer for Malloced<T> {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -105,10 +302,14 @@ impl<T: ?Sized + PartialEq> PartialEq for Malloced<T> {
     fn eq(&self, other: &Self) -> bool {
         T::eq(self, other)
     }
+}
 
+// The reverse, `PartialEq<Malloced<T>> for &T`, is not allowed by the orphan
+// rules since `T` would be uncovered.
+impl<'a, T: ?Sized + PartialEq> PartialEq<&'a T> for Malloced<T> {
     #[inline]
-    fn ne(&self, other: &Self) -> bool {
-        T::ne(self, other)
+    fn eq(&self, other: &&'a T) -> bool {
+        T::eq(self, *other)
     }
 }
 
@@ -141,6 +342,101 @@ impl<T: ?Sized + PartialOrd> PartialOrd for Malloced<T> {
     }
 }
 
+// Lengths are compared first so that no element comparison runs when the
+// slices cannot be equal.
+impl<T: PartialEq<U>, U> PartialEq<[U]> for Malloced<[T]> {
+    #[inline]
+    fn eq(&self, other: &[U]) -> bool {
+        self.len() == other.len() && <[T]>::eq(self, other)
+    }
+}
+
+impl<T: PartialEq<U>, U> PartialEq<Malloced<[U]>> for [T] {
+    #[inline]
+    fn eq(&self, other: &Malloced<[U]>) -> bool {
+        self.len() == other.len() && <[T]>::eq(self, &**other)
+    }
+}
+
+impl<T: PartialOrd> PartialOrd<[T]> for Malloced<[T]> {
+    #[inline]
+    fn partial_cmp(&self, other: &[T]) -> Option<core::cmp::Ordering> {
+        <[T]>::partial_cmp(self, other)
+    }
+}
+
+impl<T: PartialOrd> PartialOrd<Malloced<[T]>> for [T] {
+    #[inline]
+    fn partial_cmp(&self, other: &Malloced<[T]>) -> Option<core::cmp::Ordering> {
+        <[T]>::partial_cmp(self, &**other)
+    }
+}
+
+impl PartialEq<str> for Malloced<str> {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        str::eq(self, other)
+    }
+}
+
+impl PartialEq<Malloced<str>> for str {
+    #[inline]
+    fn eq(&self, other: &Malloced<str>) -> bool {
+        str::eq(self, &**other)
+    }
+}
+
+impl PartialOrd<str> for Malloced<str> {
+    #[inline]
+    fn partial_cmp(&self, other: &str) -> Option<core::cmp::Ordering> {
+        str::partial_cmp(self, other)
+    }
+}
+
+impl PartialOrd<Malloced<str>> for str {
+    #[inline]
+    fn partial_cmp(&self, other: &Malloced<str>) -> Option<core::cmp::Ordering> {
+        str::partial_cmp(self, &**other)
+    }
+}
+
+impl PartialEq<CStr> for Malloced<CStr> {
+    #[inline]
+    fn eq(&self, other: &CStr) -> bool {
+        CStr::eq(self, other)
+    }
+}
+
+impl PartialEq<Malloced<CStr>> for CStr {
+    #[inline]
+    fn eq(&self, other: &Malloced<CStr>) -> bool {
+        CStr::eq(self, &**other)
+    }
+}
+
+impl PartialEq<Malloced<CStr>> for &CStr {
+    #[inline]
+    fn eq(&self, other: &Malloced<CStr>) -> bool {
+        CStr::eq(self, &**other)
+    }
+}
+
+impl<T: ?Sized + Index<I>, I> Index<I> for Malloced<T> {
+    type Output = T::Output;
+
+    #[inline]
+    fn index(&self, index: I) -> &Self::Output {
+        T::index(self, index)
+    }
+}
+
+impl<T: ?Sized + IndexMut<I>, I> IndexMut<I> for Malloced<T> {
+    #[inline]
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        T::index_mut(self, index)
+    }
+}
+
 impl<T: ?Sized + Ord> Ord for Malloced<T> {
     #[inline]
     fn cmp(&self, other: &Self) -> core::cmp::Ordering {
@@ -226,3 +522,29 @@ impl<T: ?Sized + Hasher> Hasher for Malloced<T> {
         T::write_isize(self, i)
     }
 }
+
+impl<T: ?Sized + BuildHasher> BuildHasher for Malloced<T> {
+    type Hasher = T::Hasher;
+
+    #[inline]
+    fn build_hasher(&self) -> Self::Hasher {
+        T::build_hasher(self)
+    }
+}
+
+impl<T: ?Sized + fmt::Write> fmt::Write for Malloced<T> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        T::write_str(self, s)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        T::write_char(self, c)
+    }
+
+    #[inline]
+    fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+        T::write_fmt(self, args)
+    }
+}
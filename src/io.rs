@@ -0,0 +1,465 @@
+use crate::{Malloced, MallocedVec};
+use std::{
+    cmp, fmt,
+    io::{self, BufRead, IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write},
+};
+
+impl Malloced<[u8]> {
+    /// Borrows the bytes as an [`IoSlice`] for vectored writes, such as
+    /// [`Write::write_vectored`], without copying.
+    ///
+    /// This requires the `std` feature.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_io_slice(this)` instead of `this.as_io_slice()`.
+    #[inline]
+    pub fn as_io_slice(this: &Self) -> IoSlice<'_> {
+        IoSlice::new(this)
+    }
+
+    /// Borrows the bytes as an [`IoSliceMut`] for vectored reads, such as
+    /// [`Read::read_vectored`], without copying.
+    ///
+    /// This requires the `std` feature.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_io_slice_mut(this)` instead of
+    /// `this.as_io_slice_mut()`.
+    #[inline]
+    pub fn as_io_slice_mut(this: &mut Self) -> IoSliceMut<'_> {
+        IoSliceMut::new(this)
+    }
+}
+
+/// An owned cursor over a
+/// <code>[Malloced](crate::Malloced)<[\[u8\]](prim@slice)></code> that
+/// implements [`Read`], [`BufRead`], and [`Seek`].
+///
+/// This behaves like [`io::Cursor`], but owns the `malloc`-ed buffer and frees
+/// it on drop.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use malloced::{Malloced, MallocedCursor};
+/// use std::io::Read;
+///
+/// let buf: Malloced<[u8]> = b"hello".iter().copied().collect();
+/// let mut cursor = MallocedCursor::new(buf);
+///
+/// let mut out = String::new();
+/// cursor.read_to_string(&mut out).unwrap();
+/// assert_eq!(out, "hello");
+/// ```
+pub struct MallocedCursor {
+    inner: Malloced<[u8]>,
+    pos: u64,
+}
+
+impl MallocedCursor {
+    /// Creates a new cursor positioned at the start of `inner`.
+    #[inline]
+    pub fn new(inner: Malloced<[u8]>) -> Self {
+        Self { inner, pos: 0 }
+    }
+
+    /// Consumes the cursor, returning the underlying buffer.
+    #[inline]
+    pub fn into_inner(self) -> Malloced<[u8]> {
+        self.inner
+    }
+
+    /// Returns a reference to the underlying buffer.
+    #[inline]
+    pub fn get_ref(&self) -> &Malloced<[u8]> {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying buffer.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut Malloced<[u8]> {
+        &mut self.inner
+    }
+
+    /// Returns the current position of the cursor.
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Sets the position of the cursor.
+    ///
+    /// The position may be past the end of the buffer, in which case reads
+    /// return no bytes.
+    #[inline]
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// Returns the bytes from the current position to the end of the buffer.
+    #[inline]
+    pub fn remaining_slice(&self) -> &[u8] {
+        let start = cmp::min(self.pos, self.inner.len() as u64) as usize;
+        &self.inner[start..]
+    }
+}
+
+impl fmt::Debug for MallocedCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MallocedCursor")
+            .field("inner", &self.inner)
+            .field("pos", &self.pos)
+            .finish()
+    }
+}
+
+impl From<Malloced<[u8]>> for MallocedCursor {
+    #[inline]
+    fn from(inner: Malloced<[u8]>) -> Self {
+        Self::new(inner)
+    }
+}
+
+impl Read for MallocedCursor {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.remaining_slice().read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let remaining = self.remaining_slice();
+        if remaining.len() < buf.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            ));
+        }
+
+        buf.copy_from_slice(&remaining[..buf.len()]);
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl BufRead for MallocedCursor {
+    #[inline]
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.remaining_slice())
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl Seek for MallocedCursor {
+    fn seek(&mut self, style: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match style {
+            SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.inner.len() as u64, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+
+        let new_pos = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.unsigned_abs())
+        };
+
+        match new_pos {
+            Some(n) => {
+                self.pos = n;
+                Ok(n)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    #[inline]
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+/// A writer that collects bytes into `malloc`-ed memory.
+///
+/// This implements [`Write`] by appending to a
+/// [`MallocedVec<u8>`](crate::MallocedVec), growing it via `realloc`. Once
+/// done, [`into_boxed_slice`](Self::into_boxed_slice) produces a buffer that
+/// can be handed to C code that will `free` it.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use malloced::MallocedWriter;
+/// use std::io::Write;
+///
+/// let mut writer = MallocedWriter::new();
+/// write!(writer, "{}-{}", 1, 2).unwrap();
+///
+/// let buf = writer.into_boxed_slice();
+/// assert_eq!(&*buf, b"1-2");
+/// ```
+#[derive(Default)]
+pub struct MallocedWriter {
+    vec: MallocedVec<u8>,
+}
+
+impl MallocedWriter {
+    /// Creates an empty writer without allocating.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            vec: MallocedVec::new(),
+        }
+    }
+
+    /// Creates an empty writer with space for at least `capacity` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            vec: MallocedVec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the bytes written so far.
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        self.vec.as_slice()
+    }
+
+    /// Returns a reference to the underlying vector.
+    #[inline]
+    pub fn get_ref(&self) -> &MallocedVec<u8> {
+        &self.vec
+    }
+
+    /// Returns a mutable reference to the underlying vector.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut MallocedVec<u8> {
+        &mut self.vec
+    }
+
+    /// Consumes the writer, returning the underlying vector.
+    #[inline]
+    pub fn into_inner(self) -> MallocedVec<u8> {
+        self.vec
+    }
+
+    /// Consumes the writer, returning the written bytes in an allocation of
+    /// exactly their length.
+    ///
+    /// See [`MallocedVec::into_boxed_slice`].
+    #[inline]
+    pub fn into_boxed_slice(self) -> Malloced<[u8]> {
+        self.vec.into_boxed_slice()
+    }
+}
+
+impl fmt::Debug for MallocedWriter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MallocedWriter")
+            .field("vec", &self.vec)
+            .finish()
+    }
+}
+
+impl From<MallocedVec<u8>> for MallocedWriter {
+    #[inline]
+    fn from(vec: MallocedVec<u8>) -> Self {
+        Self { vec }
+    }
+}
+
+impl Write for MallocedWriter {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.vec.extend(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.vec.extend(buf);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(bytes: &[u8]) -> MallocedCursor {
+        MallocedCursor::new(Malloced::alloc(bytes).unwrap())
+    }
+
+    mod cursor {
+        use super::*;
+
+        #[test]
+        fn read_chunks() {
+            let mut cursor = cursor(b"abcdefg");
+            let mut chunk = [0; 3];
+
+            assert_eq!(cursor.read(&mut chunk).unwrap(), 3);
+            assert_eq!(&chunk, b"abc");
+            assert_eq!(cursor.read(&mut chunk).unwrap(), 3);
+            assert_eq!(&chunk, b"def");
+            assert_eq!(cursor.read(&mut chunk).unwrap(), 1);
+            assert_eq!(&chunk[..1], b"g");
+            assert_eq!(cursor.position(), 7);
+        }
+
+        #[test]
+        fn eof() {
+            let mut cursor = cursor(b"ab");
+            let mut chunk = [0; 4];
+
+            assert_eq!(
+                cursor.read_exact(&mut chunk).unwrap_err().kind(),
+                io::ErrorKind::UnexpectedEof
+            );
+            assert_eq!(cursor.position(), 0);
+
+            cursor.read_exact(&mut chunk[..2]).unwrap();
+            assert_eq!(cursor.read(&mut chunk).unwrap(), 0);
+            assert!(cursor.fill_buf().unwrap().is_empty());
+
+            cursor.set_position(10);
+            assert_eq!(cursor.read(&mut chunk).unwrap(), 0);
+        }
+
+        #[test]
+        fn seek() {
+            let mut cursor = cursor(b"abcdefg");
+            let mut chunk = [0; 2];
+
+            assert_eq!(cursor.seek(SeekFrom::Start(2)).unwrap(), 2);
+            cursor.read_exact(&mut chunk).unwrap();
+            assert_eq!(&chunk, b"cd");
+
+            assert_eq!(cursor.seek(SeekFrom::End(-2)).unwrap(), 5);
+            cursor.read_exact(&mut chunk).unwrap();
+            assert_eq!(&chunk, b"fg");
+
+            assert_eq!(cursor.seek(SeekFrom::Current(-7)).unwrap(), 0);
+            cursor.read_exact(&mut chunk).unwrap();
+            assert_eq!(&chunk, b"ab");
+
+            assert!(cursor.seek(SeekFrom::Current(-3)).is_err());
+            assert_eq!(cursor.position(), 2);
+        }
+
+        #[test]
+        fn buf_read() {
+            let mut cursor = cursor(b"one\ntwo\n");
+
+            assert_eq!(cursor.fill_buf().unwrap(), b"one\ntwo\n");
+            cursor.consume(4);
+            assert_eq!(cursor.fill_buf().unwrap(), b"two\n");
+
+            let mut line = String::new();
+            cursor.read_line(&mut line).unwrap();
+            assert_eq!(line, "two\n");
+            assert!(cursor.fill_buf().unwrap().is_empty());
+
+            assert_eq!(&*cursor.into_inner(), b"one\ntwo\n");
+        }
+    }
+
+    mod io_slice {
+        use super::*;
+
+        #[test]
+        fn write_vectored() {
+            let head = Malloced::<[u8]>::alloc(b"head").unwrap();
+            let tail = Malloced::<[u8]>::alloc(b"-tail").unwrap();
+
+            let slice = Malloced::as_io_slice(&head);
+            assert_eq!(&*slice, b"head");
+
+            let mut out = Vec::new();
+            let bufs = [slice, Malloced::as_io_slice(&tail)];
+            let n = out.write_vectored(&bufs).unwrap();
+            assert_eq!(n, 9);
+            assert_eq!(out, b"head-tail");
+        }
+
+        #[test]
+        fn read_vectored() {
+            let mut a = Malloced::<[u8]>::alloc(&[0; 2]).unwrap();
+            let mut b = Malloced::<[u8]>::alloc(&[0; 3]).unwrap();
+
+            let mut reader: &[u8] = b"abcde";
+            let n = {
+                let mut bufs = [
+                    Malloced::as_io_slice_mut(&mut a),
+                    Malloced::as_io_slice_mut(&mut b),
+                ];
+                reader.read_vectored(&mut bufs).unwrap()
+            };
+
+            assert_eq!(n, 5);
+            assert_eq!(&*a, b"ab");
+            assert_eq!(&*b, b"cde");
+        }
+    }
+
+    mod writer {
+        use super::*;
+
+        #[test]
+        fn chunks() {
+            let mut writer = MallocedWriter::new();
+            assert_eq!(writer.write(b"abc").unwrap(), 3);
+            writer.write_all(b"").unwrap();
+            writer.write_all(b"defg").unwrap();
+            writer.flush().unwrap();
+            assert_eq!(writer.as_slice(), b"abcdefg");
+
+            for i in 0..100u8 {
+                writer.write_all(&[i]).unwrap();
+            }
+
+            let buf = writer.into_boxed_slice();
+            assert_eq!(&buf[..7], b"abcdefg");
+            assert!(buf[7..].iter().copied().eq(0..100));
+        }
+
+        #[test]
+        fn formatted() {
+            let mut writer = MallocedWriter::with_capacity(4);
+            let name = "x";
+            write!(writer, "{:03}|{}", 7, name).unwrap();
+            assert_eq!(&*writer.into_boxed_slice(), b"007|x");
+        }
+
+        #[test]
+        fn empty() {
+            let buf = MallocedWriter::default().into_boxed_slice();
+            assert!(buf.is_empty());
+        }
+    }
+}
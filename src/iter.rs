@@ -8,18 +8,89 @@ use core::{
 
 /// An iterator over a
 /// <code>[Malloced](crate::Malloced)<[\[T\]](prim@slice)></code>.
+///
+/// `SliceIter<T>` is covariant in `T`, and is [`Send`]/[`Sync`] whenever `T`
+/// is. As such, it cannot be sent across threads if `T` cannot:
+///
+/// ```compile_fail
+/// # use malloced::SliceIter;
+/// # use std::rc::Rc;
+/// fn assert_send<T: Send>() {}
+///
+/// assert_send::<SliceIter<Rc<i32>>>();
+/// ```
 pub struct SliceIter<T> {
     pub(super) buf: NonNull<T>,
     // Marks ownership of an instance of T.
     pub(super) marker: PhantomData<T>,
-    pub(super) ptr: *mut T,
-    pub(super) end: *mut T,
+    // `*const` rather than `*mut` so that `SliceIter<T>` is covariant in `T`.
+    pub(super) ptr: *const T,
+    pub(super) end: *const T,
 }
 
+unsafe impl<T: Send> Send for SliceIter<T> {}
+unsafe impl<T: Sync> Sync for SliceIter<T> {}
+
 impl<T> SliceIter<T> {
+    /// Returns a raw pointer to the first remaining element.
+    ///
+    /// Together with [`len`](ExactSizeIterator::len), this describes the
+    /// elements that have not yet been yielded from either end.
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        self.front_ptr()
+    }
+
+    /// Returns a mutable raw pointer to the first remaining element.
+    ///
+    /// Together with [`len`](ExactSizeIterator::len), this describes the
+    /// elements that have not yet been yielded from either end.
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.front_ptr()
+    }
+
+    /// Returns a reference to the next element from the front without
+    /// consuming it.
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        if self.ptr == self.end {
+            None
+        } else if mem::size_of::<T>() == 0 {
+            // Any aligned non-null pointer is valid for ZST references.
+            Some(unsafe { &*NonNull::dangling().as_ptr() })
+        } else {
+            Some(unsafe { &*self.ptr })
+        }
+    }
+
+    /// Returns a reference to the next element from the back without
+    /// consuming it.
+    #[inline]
+    pub fn peek_back(&self) -> Option<&T> {
+        if self.ptr == self.end {
+            None
+        } else if mem::size_of::<T>() == 0 {
+            // Any aligned non-null pointer is valid for ZST references.
+            Some(unsafe { &*NonNull::dangling().as_ptr() })
+        } else {
+            Some(unsafe { &*self.end.sub(1) })
+        }
+    }
+
+    #[inline]
+    fn front_ptr(&self) -> *mut T {
+        if mem::size_of::<T>() == 0 {
+            // `ptr` is used as a counter for ZSTs, so it may not be aligned.
+            self.buf.as_ptr()
+        } else {
+            self.ptr as *mut T
+        }
+    }
+
     #[inline]
     fn as_raw_mut_slice(&mut self) -> &mut [T] {
-        unsafe { slice::from_raw_parts_mut(self.ptr, self.len()) }
+        unsafe { slice::from_raw_parts_mut(self.ptr as *mut T, self.len()) }
     }
 }
 
@@ -31,6 +102,13 @@ impl<T> Drop for SliceIter<T> {
         impl<'a, T> Drop for DeallocGuard<'a, T> {
             #[inline]
             fn drop(&mut self) {
+                // The default iterator does not own an allocation. `malloc`
+                // never returns an address this low, so it cannot be confused
+                // with a real buffer.
+                if self.0.buf == NonNull::dangling() {
+                    return;
+                }
+
                 unsafe {
                     sys::free(self.0.buf.as_ptr() as _);
                 }
@@ -48,6 +126,20 @@ impl<T> Drop for SliceIter<T> {
     }
 }
 
+impl<T> Default for SliceIter<T> {
+    /// Creates an empty iterator that does not own an allocation.
+    #[inline]
+    fn default() -> Self {
+        let buf = NonNull::dangling();
+        Self {
+            buf,
+            marker: PhantomData,
+            ptr: buf.as_ptr(),
+            end: buf.as_ptr(),
+        }
+    }
+}
+
 impl<T> Iterator for SliceIter<T> {
     type Item = T;
 
@@ -58,7 +150,7 @@ impl<T> Iterator for SliceIter<T> {
         } else if mem::size_of::<T>() == 0 {
             // Purposefully don't use `ptr.offset` because for slices with
             // 0-size elements this would return the same pointer.
-            self.ptr = (self.ptr as *mut i8).wrapping_add(1) as *mut T;
+            self.ptr = (self.ptr as *const i8).wrapping_add(1) as *const T;
 
             // Make up a value of this ZST.
             Some(unsafe { mem::zeroed() })
@@ -85,6 +177,33 @@ impl<T> Iterator for SliceIter<T> {
     fn last(mut self) -> Option<Self::Item> {
         self.next_back()
     }
+
+    #[inline]
+    fn fold<B, F>(mut self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B,
+    {
+        let mut acc = init;
+
+        if mem::size_of::<T>() == 0 {
+            for item in &mut self {
+                acc = f(acc, item);
+            }
+            return acc;
+        }
+
+        // Advance `ptr` before calling `f` so that if it panics, `Drop` only
+        // drops the elements that have not yet been read.
+        while self.ptr != self.end {
+            unsafe {
+                let item = self.ptr.read();
+                self.ptr = self.ptr.add(1);
+                acc = f(acc, item);
+            }
+        }
+
+        acc
+    }
 }
 
 impl<T> DoubleEndedIterator for SliceIter<T> {
@@ -95,7 +214,7 @@ impl<T> DoubleEndedIterator for SliceIter<T> {
         } else if mem::size_of::<T>() == 0 {
             // Purposefully don't use `ptr.offset` because for slices with
             // 0-size elements this would return the same pointer.
-            self.ptr = (self.ptr as *mut i8).wrapping_sub(1) as *mut T;
+            self.end = (self.end as *const i8).wrapping_sub(1) as *const T;
 
             // Make up a value of this ZST.
             Some(unsafe { mem::zeroed() })
@@ -105,6 +224,34 @@ impl<T> DoubleEndedIterator for SliceIter<T> {
             Some(unsafe { self.end.read() })
         }
     }
+
+    #[inline]
+    fn rfold<B, F>(mut self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B,
+    {
+        let mut acc = init;
+
+        if mem::size_of::<T>() == 0 {
+            // All ZST values are indistinguishable, so the order in which they
+            // are yielded does not matter.
+            for item in &mut self {
+                acc = f(acc, item);
+            }
+            return acc;
+        }
+
+        // Retreat `end` before calling `f` so that if it panics, `Drop` only
+        // drops the elements that have not yet been read.
+        while self.ptr != self.end {
+            unsafe {
+                self.end = self.end.sub(1);
+                acc = f(acc, self.end.read());
+            }
+        }
+
+        acc
+    }
 }
 
 impl<T> ExactSizeIterator for SliceIter<T> {
@@ -123,6 +270,11 @@ impl<T> ExactSizeIterator for SliceIter<T> {
 
 impl<T> core::iter::FusedIterator for SliceIter<T> {}
 
+// SAFETY: `len` is exact for all element sizes. For ZSTs, `ptr` and `end` act
+// as counters that `next` and `next_back` move towards each other by one.
+#[cfg(feature = "nightly")]
+unsafe impl<T> core::iter::TrustedLen for SliceIter<T> {}
+
 #[cfg(test)]
 mod tests {
     use crate::Malloced;
@@ -163,9 +315,315 @@ mod tests {
         }
     }
 
+    mod fold {
+        use super::*;
+
+        #[track_caller]
+        fn test<T: Copy + Debug + PartialEq>(slice: &[T]) {
+            let iter = Malloced::alloc(slice).unwrap().into_iter();
+            let folded = iter.fold(Vec::new(), |mut acc, x| {
+                acc.push(x);
+                acc
+            });
+            assert_eq!(folded, slice);
+
+            let iter = Malloced::alloc(slice).unwrap().into_iter();
+            let rfolded = iter.rfold(Vec::new(), |mut acc, x| {
+                acc.push(x);
+                acc
+            });
+            let mut expected = slice.to_vec();
+            expected.reverse();
+            assert_eq!(rfolded, expected);
+
+            // Compare against folding via `next`.
+            let iter = Malloced::alloc(slice).unwrap().into_iter();
+            let mut expected = Vec::new();
+            for x in iter {
+                expected.push(x);
+            }
+            assert_eq!(folded, expected);
+        }
+
+        #[test]
+        fn zst() {
+            test::<()>(&[]);
+            test(&[(), (), ()]);
+        }
+
+        #[test]
+        fn u8() {
+            test::<u8>(&[]);
+            test(&[1u8, 2, 3]);
+        }
+
+        #[test]
+        fn usize() {
+            test::<usize>(&[]);
+            test(&[1usize, 2, 3]);
+        }
+
+        #[test]
+        fn partial() {
+            let mut iter = Malloced::alloc(&[1u32, 2, 3, 4]).unwrap().into_iter();
+            iter.next();
+            iter.next_back();
+            let folded = iter.fold(Vec::new(), |mut acc, x| {
+                acc.push(x);
+                acc
+            });
+            assert_eq!(folded, [2, 3]);
+        }
+
+        #[cfg(feature = "std")]
+        mod panic {
+            use super::*;
+            use crate::tests::DropCounter;
+            use core::cell::Cell;
+
+            #[track_caller]
+            fn test_panic<F>(fold: F)
+            where
+                F: for<'a> FnOnce(
+                    crate::SliceIter<DropCounter<'a>>,
+                    &mut dyn FnMut(DropCounter<'a>),
+                ),
+            {
+                let drops = Cell::new(0);
+                let buf = Malloced::alloc(&[
+                    DropCounter(&drops),
+                    DropCounter(&drops),
+                    DropCounter(&drops),
+                    DropCounter(&drops),
+                ])
+                .unwrap();
+                drops.set(0);
+
+                let mut seen = 0;
+                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    fold(buf.into_iter(), &mut |_item| {
+                        seen += 1;
+                        if seen == 2 {
+                            panic!("fold panic");
+                        }
+                    })
+                }));
+
+                assert!(result.is_err());
+                assert_eq!(drops.get(), 4);
+            }
+
+            #[test]
+            fn fold_panic() {
+                test_panic(|iter, f| iter.fold((), |(), x| f(x)));
+            }
+
+            #[test]
+            fn rfold_panic() {
+                test_panic(|iter, f| iter.rfold((), |(), x| f(x)));
+            }
+        }
+    }
+
+    mod auto_traits {
+        use super::*;
+        use crate::SliceIter;
+
+        #[test]
+        fn send_sync() {
+            fn assert_send_sync<T: Send + Sync>() {}
+
+            assert_send_sync::<SliceIter<i32>>();
+            assert_send_sync::<SliceIter<alloc::string::String>>();
+        }
+
+        #[test]
+        fn covariant() {
+            fn shorten<'a>(iter: SliceIter<&'static str>) -> SliceIter<&'a str> {
+                iter
+            }
+
+            let iter = Malloced::alloc(&["a", "b"]).unwrap().into_iter();
+            assert_eq!(shorten(iter).collect::<Vec<_>>(), ["a", "b"]);
+        }
+    }
+
+    mod default {
+        use crate::SliceIter;
+        use alloc::string::String;
+
+        #[test]
+        fn empty() {
+            let mut iter = SliceIter::<String>::default();
+            assert_eq!(iter.len(), 0);
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next_back(), None);
+
+            let mut iter = SliceIter::<()>::default();
+            assert_eq!(iter.len(), 0);
+            assert_eq!(iter.next(), None);
+        }
+
+        #[test]
+        fn take() {
+            let mut iter = crate::Malloced::alloc(&[1u8, 2]).unwrap().into_iter();
+            let taken = core::mem::take(&mut iter);
+            assert_eq!(iter.len(), 0);
+            assert_eq!(taken.len(), 2);
+        }
+    }
+
+    mod peek {
+        use super::*;
+        use alloc::string::String;
+
+        #[test]
+        fn peek() {
+            let values = [String::from("a"), String::from("b"), String::from("c")];
+            let mut iter = Malloced::alloc(&values).unwrap().into_iter();
+
+            assert_eq!(iter.peek().map(String::as_str), Some("a"));
+            assert_eq!(iter.peek_back().map(String::as_str), Some("c"));
+            assert_eq!(iter.len(), 3);
+
+            assert_eq!(iter.next().as_deref(), Some("a"));
+            assert_eq!(iter.next_back().as_deref(), Some("c"));
+
+            assert_eq!(iter.peek().map(String::as_str), Some("b"));
+            assert_eq!(iter.peek_back().map(String::as_str), Some("b"));
+            assert_eq!(iter.next().as_deref(), Some("b"));
+
+            assert_eq!(iter.peek(), None);
+            assert_eq!(iter.peek_back(), None);
+        }
+
+        #[test]
+        fn zst() {
+            let mut iter = Malloced::alloc(&[(), ()]).unwrap().into_iter();
+            assert_eq!(iter.peek(), Some(&()));
+            assert_eq!(iter.peek_back(), Some(&()));
+
+            iter.next();
+            iter.next();
+            assert_eq!(iter.peek(), None);
+            assert_eq!(iter.peek_back(), None);
+        }
+    }
+
+    mod as_ptr {
+        use super::*;
+
+        #[test]
+        fn advances() {
+            let mut iter = Malloced::alloc(&[1u32, 2, 3]).unwrap().into_iter();
+            let start = iter.as_ptr();
+
+            iter.next();
+            assert_eq!(iter.as_ptr(), start.wrapping_add(1));
+            assert_eq!(iter.as_mut_ptr() as *const u32, iter.as_ptr());
+
+            iter.next_back();
+            assert_eq!(iter.as_ptr(), start.wrapping_add(1));
+
+            let remaining = unsafe { core::slice::from_raw_parts(iter.as_ptr(), iter.len()) };
+            assert_eq!(remaining, [2]);
+        }
+
+        #[test]
+        fn zst() {
+            let mut iter = Malloced::alloc(&[(), ()]).unwrap().into_iter();
+            let start = iter.as_ptr();
+
+            iter.next();
+            assert_eq!(iter.as_ptr(), start);
+        }
+    }
+
     mod len {
         use super::*;
 
+        #[test]
+        fn zst_next_back() {
+            let mut iter = Malloced::alloc(&[(), (), ()]).unwrap().into_iter();
+
+            assert_eq!(iter.next_back(), Some(()));
+            assert_eq!(iter.len(), 2);
+
+            assert_eq!(iter.next(), Some(()));
+            assert_eq!(iter.len(), 1);
+
+            assert_eq!(iter.next_back(), Some(()));
+            assert_eq!(iter.len(), 0);
+            assert_eq!(iter.next_back(), None);
+            assert_eq!(iter.next(), None);
+        }
+
+        /// Alternates `next` and `next_back`, checking that `len` and
+        /// `size_hint` drop by exactly one per call.
+        #[track_caller]
+        fn alternate<T: Copy + Debug + PartialEq>(slice: &[T]) {
+            let mut iter = Malloced::alloc(slice).unwrap().into_iter();
+
+            for (i, back) in [false, true].iter().cycle().take(slice.len()).enumerate() {
+                let expected = slice.len() - i;
+                assert_eq!(iter.len(), expected);
+                assert_eq!(iter.size_hint(), (expected, Some(expected)));
+
+                let item = if *back { iter.next_back() } else { iter.next() };
+                assert!(item.is_some());
+                assert_eq!(iter.len(), expected - 1);
+            }
+
+            assert_eq!(iter.size_hint(), (0, Some(0)));
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next_back(), None);
+            assert_eq!(iter.len(), 0);
+        }
+
+        #[test]
+        fn alternating() {
+            alternate(&[(); 5]);
+            alternate(&[(); 4]);
+            alternate(&[1u8, 2, 3, 4, 5]);
+            alternate(&[1u64, 2, 3, 4]);
+        }
+
+        #[test]
+        fn zst_back_then_front() {
+            let mut iter = Malloced::alloc(&[(); 5]).unwrap().into_iter();
+
+            for expected in (3..5).rev() {
+                iter.next_back();
+                assert_eq!(iter.len(), expected);
+            }
+            for expected in (0..3).rev() {
+                iter.next();
+                assert_eq!(iter.len(), expected);
+            }
+
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next_back(), None);
+        }
+
+        #[cfg(feature = "nightly")]
+        #[test]
+        fn trusted_len() {
+            fn assert_trusted_len<I: core::iter::TrustedLen>(_: &I) {}
+
+            let values: Vec<u32> = (0..10).collect();
+            let iter = Malloced::alloc(&values).unwrap().into_iter();
+            assert_trusted_len(&iter);
+
+            let vec: Vec<u32> = iter.collect();
+            assert_eq!(vec, values);
+            assert_eq!(vec.capacity(), values.len());
+
+            let mut iter = Malloced::alloc(&[(), (), ()]).unwrap().into_iter();
+            iter.next_back();
+            let vec: Vec<()> = iter.collect();
+            assert_eq!(vec.len(), 2);
+        }
+
         #[track_caller]
         fn test(slice: &[impl Copy]) {
             let iter = Malloced::alloc(slice).unwrap().into_iter();
@@ -196,4 +654,114 @@ mod tests {
             test(&[1usize, 2usize]);
         }
     }
+
+    mod array {
+        use super::*;
+
+        #[test]
+        fn u8() {
+            let array = Malloced::alloc_one([1u8, 2, 3]).unwrap();
+
+            let mut values = Vec::new();
+            for value in array {
+                values.push(value);
+            }
+            assert_eq!(values, [1, 2, 3]);
+        }
+
+        #[test]
+        fn zst() {
+            let array = Malloced::alloc_one([(); 4]).unwrap();
+            let mut iter = array.into_iter();
+            assert_eq!(iter.len(), 4);
+            assert_eq!(iter.next_back(), Some(()));
+            assert_eq!(iter.count(), 3);
+        }
+
+        #[test]
+        fn empty() {
+            let array = Malloced::alloc_one([0u32; 0]).unwrap();
+            assert_eq!(array.into_iter().next(), None);
+        }
+    }
+
+    mod double_ended {
+        use super::*;
+        use core::cell::Cell;
+
+        /// Records how many times the element at each index was dropped.
+        struct Tracked<'a> {
+            drops: &'a [Cell<usize>],
+            index: usize,
+        }
+
+        impl Drop for Tracked<'_> {
+            fn drop(&mut self) {
+                let drops = &self.drops[self.index];
+                drops.set(drops.get() + 1);
+            }
+        }
+
+        /// Takes `steps` elements, choosing the back for each set bit of
+        /// `mask`, and then drops the iterator.
+        fn interleave(len: usize, mask: usize, steps: usize) {
+            let drops: Vec<Cell<usize>> = (0..len).map(|_| Cell::new(0)).collect();
+            let buf = Malloced::<[Tracked]>::from_fn(len, |index| Tracked {
+                drops: &drops,
+                index,
+            });
+
+            let mut iter = buf.into_iter();
+            let (mut front, mut back) = (0, len);
+
+            for step in 0..steps {
+                let (index, expected) = if mask & (1 << step) == 0 {
+                    front += 1;
+                    (iter.next().unwrap().index, front - 1)
+                } else {
+                    back -= 1;
+                    (iter.next_back().unwrap().index, back)
+                };
+
+                assert_eq!(index, expected);
+                assert_eq!(iter.len(), len - step - 1);
+            }
+
+            if steps == len {
+                assert!(iter.next().is_none());
+                assert!(iter.next_back().is_none());
+            }
+
+            // Only the yielded elements have been dropped so far.
+            for (index, drops) in drops.iter().enumerate() {
+                let yielded = index < front || index >= back;
+                assert_eq!(drops.get(), yielded as usize, "index {}", index);
+            }
+
+            drop(iter);
+            assert!(drops.iter().all(|drops| drops.get() == 1));
+        }
+
+        #[test]
+        fn interleaved() {
+            for len in 0..=3 {
+                for mask in 0..1 << len {
+                    for steps in 0..=len {
+                        interleave(len, mask, steps);
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn rev() {
+            let values: Vec<u32> = (0..5).collect();
+            let rev: Vec<u32> = Malloced::alloc(&values)
+                .unwrap()
+                .into_iter()
+                .rev()
+                .collect();
+            assert_eq!(rev, [4, 3, 2, 1, 0]);
+        }
+    }
 }
@@ -72,28 +72,254 @@
 //! [`Malloced`]: struct.Malloced.html
 
 #![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "nightly", feature(min_specialization, trusted_len))]
 
-#[cfg(test)]
+#[cfg(any(test, feature = "alloc"))]
 extern crate alloc;
 
+#[cfg(feature = "bytemuck")]
+extern crate bytemuck;
+
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
 #[cfg(feature = "std")]
 use std as core;
 
 use core::{
     any::Any,
-    ffi::{c_char, CStr},
+    ffi::{c_char, c_void, CStr, FromBytesWithNulError},
     marker::PhantomData,
     mem,
-    mem::ManuallyDrop,
+    mem::{ManuallyDrop, MaybeUninit},
     pin::Pin,
     ptr::NonNull,
 };
 
+#[cfg(feature = "std")]
+use std::error::Error;
+
+mod any;
+#[cfg(feature = "base64")]
+mod base64;
+mod by_address;
+#[cfg(feature = "crc")]
+mod crc;
+#[cfg(feature = "hex")]
+mod hex;
 mod impls;
+#[cfg(feature = "std")]
+mod io;
 mod iter;
+#[cfg(feature = "nightly")]
+mod nightly;
+mod ops;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "rc")]
+mod rc;
+mod slice;
 mod sys;
+mod vec;
 
+pub use any::CloneAny;
+#[cfg(feature = "base64")]
+pub use base64::{Base64Alphabet, DecodeError};
+pub use by_address::*;
+#[cfg(feature = "hex")]
+pub use hex::FromHexError;
+#[cfg(feature = "std")]
+pub use io::*;
 pub use iter::*;
+#[cfg(feature = "rc")]
+pub use rc::*;
+pub use slice::*;
+pub use vec::*;
+
+#[cfg(feature = "rayon")]
+pub use par::*;
+
+/// Allocates `size` bytes with `malloc`, panicking on failure.
+///
+/// Zero-sized requests allocate 1 byte so that the result is never null.
+#[inline]
+fn malloc_or_panic(size: usize) -> *mut u8 {
+    let ptr = unsafe { sys::malloc(size.max(1)) };
+    if ptr.is_null() {
+        alloc_failed(size);
+    }
+    ptr
+}
+
+/// Returns the allocation size that `malloc` would round a request of
+/// `requested` bytes up to.
+///
+/// Sizing allocations to this value avoids wasting the slack at the end of the
+/// allocator's size class. This uses `malloc_good_size` on macOS and iOS. On
+/// other platforms, the allocator's size classes are not queryable, so this
+/// returns `requested` unchanged.
+#[inline]
+pub fn good_size(requested: usize) -> usize {
+    sys::good_size(requested)
+}
+
+/// Returns the size in bytes of `len` values of `T`, or `None` if it exceeds
+/// `isize::MAX`.
+#[inline]
+fn array_size<T>(len: usize) -> Option<usize> {
+    match len.checked_mul(mem::size_of::<T>()) {
+        Some(size) if size <= isize::MAX as usize => Some(size),
+        _ => None,
+    }
+}
+
+/// Allocates uninitialized memory for `len` values of `T` with `malloc`,
+/// panicking on failure.
+///
+/// Panics if the size overflows or if `malloc` does not return memory aligned
+/// for `T`, which may happen for over-aligned types.
+fn malloc_array<T>(len: usize) -> NonNull<T> {
+    let size = array_size::<T>(len).unwrap_or_else(|| capacity_overflow());
+    malloc_aligned(size)
+}
+
+/// Allocates `size` bytes with `malloc` for values of `T`, panicking on
+/// failure.
+///
+/// Panics if `malloc` does not return memory aligned for `T`.
+fn malloc_aligned<T>(size: usize) -> NonNull<T> {
+    let ptr = malloc_or_panic(size);
+    if ptr as usize % mem::align_of::<T>() != 0 {
+        unsafe { sys::free(ptr as *mut c_void) };
+        misaligned(mem::align_of::<T>());
+    }
+
+    unsafe { NonNull::new_unchecked(ptr.cast()) }
+}
+
+/// Frees `malloc`-ed memory when dropped, unless it is forgotten.
+///
+/// This cleans up an allocation if initializing it fails or panics.
+struct FreeGuard(*mut c_void);
+
+impl Drop for FreeGuard {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { sys::free(self.0) };
+    }
+}
+
+/// Aborts the process if dropped, which only happens during unwinding unless
+/// it is forgotten.
+///
+/// `core` has no stable abort, so this panics from its destructor instead. A
+/// panic while already unwinding aborts.
+struct AbortOnUnwind;
+
+impl Drop for AbortOnUnwind {
+    #[inline]
+    fn drop(&mut self) {
+        panic!("panicked in a context that cannot unwind");
+    }
+}
+
+/// The alignment that `malloc` guarantees on all supported platforms.
+const MALLOC_ALIGN: usize = 2 * mem::size_of::<usize>();
+
+/// Resizes the `malloc`-ed memory at `ptr` to hold `len` values of `T`,
+/// panicking on failure.
+///
+/// If this panics, `ptr` remains valid and owned by the caller.
+fn realloc_array<T>(ptr: NonNull<T>, len: usize) -> NonNull<T> {
+    // Unlike with `malloc_array`, a misaligned result cannot be recovered from
+    // since the original memory would already be freed.
+    if mem::align_of::<T>() > MALLOC_ALIGN {
+        misaligned(mem::align_of::<T>());
+    }
+
+    let size = array_size::<T>(len).unwrap_or_else(|| capacity_overflow());
+
+    // Never request 0 bytes, since `realloc` may then free the memory.
+    let new_ptr = unsafe { sys::realloc(ptr.as_ptr() as *mut c_void, size.max(1)) };
+    if new_ptr.is_null() {
+        alloc_failed(size);
+    }
+
+    unsafe { NonNull::new_unchecked(new_ptr.cast()) }
+}
+
+/// Allocates uninitialized memory for `len` values of `T` with `malloc`.
+///
+/// Unlike [`malloc_array`], this returns an error instead of panicking.
+fn try_malloc_array<T>(len: usize) -> Result<NonNull<T>, AllocError> {
+    let size = array_size::<T>(len).ok_or(AllocError)?;
+
+    let ptr = unsafe { sys::malloc(size.max(1)) };
+    if ptr.is_null() {
+        return Err(AllocError);
+    }
+
+    if ptr as usize % mem::align_of::<T>() != 0 {
+        unsafe { sys::free(ptr as *mut c_void) };
+        return Err(AllocError);
+    }
+
+    Ok(unsafe { NonNull::new_unchecked(ptr.cast()) })
+}
+
+/// Resizes the `malloc`-ed memory at `ptr` to hold `len` values of `T`.
+///
+/// Unlike [`realloc_array`], this returns an error instead of panicking. On
+/// failure, `ptr` remains valid and owned by the caller.
+fn try_realloc_array<T>(ptr: NonNull<T>, len: usize) -> Result<NonNull<T>, AllocError> {
+    if mem::align_of::<T>() > MALLOC_ALIGN {
+        return Err(AllocError);
+    }
+
+    let size = array_size::<T>(len).ok_or(AllocError)?;
+
+    let new_ptr = unsafe { sys::realloc(ptr.as_ptr() as *mut c_void, size.max(1)) };
+    if new_ptr.is_null() {
+        return Err(AllocError);
+    }
+
+    Ok(unsafe { NonNull::new_unchecked(new_ptr.cast()) })
+}
+
+/// The error returned when memory could not be allocated.
+///
+/// This happens when `malloc` or `realloc` fails, when the requested size
+/// overflows, or when `malloc` cannot satisfy the required alignment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocError;
+
+impl core::fmt::Display for AllocError {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for AllocError {}
+
+#[cold]
+#[inline(never)]
+fn alloc_failed(size: usize) -> ! {
+    panic!("memory allocation of {} bytes failed", size)
+}
+
+#[cold]
+#[inline(never)]
+fn capacity_overflow() -> ! {
+    panic!("capacity overflow")
+}
+
+#[cold]
+#[inline(never)]
+fn misaligned(align: usize) -> ! {
+    panic!("`malloc` does not guarantee {}-byte alignment", align)
+}
 
 /// A pointer type for `malloc`-ed heap allocation.
 ///
@@ -151,12 +377,24 @@ impl<T> IntoIterator for Malloced<[T]> {
     }
 }
 
+impl<T, const N: usize> IntoIterator for Malloced<[T; N]> {
+    type Item = T;
+    type IntoIter = SliceIter<T>;
+
+    /// Iterates over the elements by value, reusing the allocation as a slice
+    /// of `N` elements.
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        Malloced::into_slice(self).into_iter()
+    }
+}
+
 /// Testing helpers.
 #[cfg(test)]
 impl<T> Malloced<[T]> {
     fn alloc(values: &[T]) -> Option<Self>
     where
-        T: Copy,
+        T: Clone,
     {
         let value_size = mem::size_of::<T>();
         let alloc_size = values.len().checked_mul(value_size.max(1))?;
@@ -167,14 +405,14 @@ impl<T> Malloced<[T]> {
                 return None;
             }
 
-            for (i, &value) in values.iter().enumerate() {
+            for (i, value) in values.iter().enumerate() {
                 let ptr: *mut T = if value_size == 0 {
                     buf.cast::<u8>().add(i).cast()
                 } else {
                     buf.add(i)
                 };
 
-                ptr.write(value);
+                ptr.write(value.clone());
             }
 
             Some(Malloced::slice_from_raw_parts(buf, values.len()))
@@ -182,6 +420,37 @@ impl<T> Malloced<[T]> {
     }
 }
 
+/// Testing helpers.
+#[cfg(test)]
+impl<T> Malloced<T> {
+    fn alloc_one(value: T) -> Option<Self> {
+        unsafe {
+            let buf = sys::malloc(mem::size_of::<T>().max(1)).cast::<T>();
+            if buf.is_null() {
+                return None;
+            }
+
+            buf.write(value);
+
+            Some(Malloced::from_raw(buf))
+        }
+    }
+}
+
+/// Testing helpers.
+#[cfg(test)]
+impl Malloced<str> {
+    fn alloc_str(s: &str) -> Option<Self> {
+        let (buf, len) = Malloced::into_raw_parts(Malloced::<[u8]>::alloc(s.as_bytes())?);
+        unsafe {
+            let bytes = core::slice::from_raw_parts_mut(buf, len);
+            Some(Malloced::from_raw(core::str::from_utf8_unchecked_mut(
+                bytes,
+            )))
+        }
+    }
+}
+
 impl<T: ?Sized> Malloced<T> {
     /// Constructs an instance from a raw `malloc`-ed pointer.
     ///
@@ -198,6 +467,19 @@ impl<T: ?Sized> Malloced<T> {
         }
     }
 
+    /// Constructs an instance from a `malloc`-ed [`NonNull`] pointer.
+    ///
+    /// # Safety
+    ///
+    /// See [`Malloced::from_raw`](#method.from_raw).
+    #[inline]
+    pub unsafe fn from_non_null(ptr: NonNull<T>) -> Self {
+        Self {
+            ptr,
+            _marker: PhantomData,
+        }
+    }
+
     /// Consumes the instance, returning a wrapped raw pointer.
     ///
     /// The pointer will be properly aligned and non-null.
@@ -206,6 +488,54 @@ impl<T: ?Sized> Malloced<T> {
         Self::leak(this)
     }
 
+    /// Consumes the instance, returning a wrapped raw pointer along with a
+    /// function that drops and `free`s it.
+    ///
+    /// C code that takes ownership of the pointer should call the returned
+    /// function instead of `free`, so that `T`'s destructor runs and any
+    /// resources it owns are not leaked. Like `free`, the function does nothing
+    /// when given a null pointer.
+    ///
+    /// If `T`'s destructor panics, the process aborts, since unwinding out of
+    /// an `extern "C"` function is not allowed.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::into_raw_with_drop(this)` instead of
+    /// `this.into_raw_with_drop()`.
+    #[inline]
+    pub fn into_raw_with_drop(this: Self) -> (*mut T, unsafe extern "C" fn(*mut c_void))
+    where
+        T: Sized,
+    {
+        unsafe extern "C" fn drop_and_free<T>(ptr: *mut c_void) {
+            if !ptr.is_null() {
+                // Unwinding out of an `extern "C"` function is undefined
+                // behavior before Rust 1.81, so abort explicitly.
+                let abort = AbortOnUnwind;
+                drop(Malloced::from_raw(ptr as *mut T));
+                mem::forget(abort);
+            }
+        }
+
+        (Self::into_raw(this), drop_and_free::<T>)
+    }
+
+    /// Overwrites the value of `target` with a clone of `source`, reusing the
+    /// existing allocation.
+    ///
+    /// The old value is dropped, or updated in place via
+    /// [`Clone::clone_from`]. This mirrors
+    /// [`ToOwned::clone_into`](https://doc.rust-lang.org/std/borrow/trait.ToOwned.html#method.clone_into).
+    ///
+    /// See [`Malloced::clone_slice_into`](#method.clone_slice_into) for slices.
+    #[inline]
+    pub fn clone_into(source: &T, target: &mut Self)
+    where
+        T: Sized + Clone,
+    {
+        T::clone_from(target, source);
+    }
+
     /// Converts a `Malloced<T>` into a `Pin<Malloced<T>>`
     ///
     /// This conversion does not allocate on the heap and happens in place.
@@ -245,6 +575,37 @@ impl<T: ?Sized> Malloced<T> {
         unsafe { &mut *ManuallyDrop::new(this).ptr.as_ptr() }
     }
 
+    /// Consumes and leaks a pinned instance, returning a pinned mutable
+    /// reference, `Pin<&'a mut T>`.
+    ///
+    /// This is the pinned counterpart of [`Malloced::leak`](#method.leak). The
+    /// memory is never `free`-d and `T` is never dropped, so the value stays
+    /// pinned at the same address for the remainder of the program's life.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::leak_pinned(this)` instead of `this.leak_pinned()`.
+    #[inline]
+    pub fn leak_pinned<'a>(this: Pin<Self>) -> Pin<&'a mut T>
+    where
+        T: 'a,
+    {
+        // SAFETY: The value is never moved out of or freed, so it stays pinned.
+        unsafe { Pin::new_unchecked(Self::leak(Pin::into_inner_unchecked(this))) }
+    }
+
+    /// Swaps the allocations of `a` and `b` without moving their values.
+    ///
+    /// Only the pointers are exchanged, so this is O(1) regardless of the size
+    /// of `T`, whereas `mem::swap(&mut *a, &mut *b)` copies both values. As a
+    /// result, [`as_ptr`](#method.as_ptr) of each also swaps.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::swap(a, b)` instead of `a.swap(b)`.
+    #[inline]
+    pub fn swap(a: &mut Self, b: &mut Self) {
+        mem::swap(&mut a.ptr, &mut b.ptr);
+    }
+
     /// Returns an immutable raw pointer to the data.
     #[inline]
     pub fn as_ptr(this: &Self) -> *const T {
@@ -257,6 +618,76 @@ impl<T: ?Sized> Malloced<T> {
         this.ptr.as_ptr()
     }
 
+    /// Returns an immutable untyped pointer to the data, for C functions that
+    /// take `const void *`.
+    ///
+    /// For slices and other unsized types, this discards the pointer metadata
+    /// such as the length.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_c_void(this)` instead of `this.as_c_void()`.
+    #[inline]
+    pub fn as_c_void(this: &Self) -> *const c_void {
+        Self::as_ptr(this) as *const c_void
+    }
+
+    /// Returns a mutable untyped pointer to the data, for C functions that take
+    /// `void *`.
+    ///
+    /// For slices and other unsized types, this discards the pointer metadata
+    /// such as the length.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_mut_c_void(this)` instead of `this.as_mut_c_void()`.
+    #[inline]
+    pub fn as_mut_c_void(this: &mut Self) -> *mut c_void {
+        Self::as_mut_ptr(this) as *mut c_void
+    }
+
+    /// Returns a [`NonNull`] pointer to the data.
+    ///
+    /// The pointer remains owned by `this`, so it must not be `free`-d.
+    #[inline]
+    pub fn as_non_null(this: &Self) -> NonNull<T> {
+        this.ptr
+    }
+
+    /// Returns the size of the value in bytes, as given by
+    /// [`mem::size_of_val`](https://doc.rust-lang.org/std/mem/fn.size_of_val.html).
+    ///
+    /// This is the logical payload size, such as the number of bytes in a
+    /// `Malloced<str>` or the elements of a slice multiplied by their size. It
+    /// does not include any slack that the allocator may have added, which is
+    /// reported by [`usable_size`](#method.usable_size) instead.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::allocation_size(this)` instead of
+    /// `this.allocation_size()`.
+    #[inline]
+    pub fn allocation_size(this: &Self) -> usize {
+        mem::size_of_val::<T>(this)
+    }
+
+    /// Returns the number of bytes usable in the allocation, which may be
+    /// larger than the size of `T`.
+    ///
+    /// This uses `malloc_usable_size` on Linux and Android, `malloc_size` on
+    /// macOS and iOS, and `_msize` on Windows.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::usable_size(this)` instead of `this.usable_size()`.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        windows,
+    ))]
+    #[inline]
+    pub fn usable_size(this: &Self) -> usize {
+        unsafe { sys::usable_size(this.ptr.as_ptr() as *mut c_void) }
+    }
+
     // TODO: Implement `core::ops::CoerceUnsized`.
     // See https://github.com/rust-lang/rust/issues/27732.
 
@@ -281,113 +712,1857 @@ impl<T: ?Sized> Malloced<T> {
         mem::forget(this);
         unsafe { Malloced::from_raw(ptr) }
     }
-}
 
-impl<T> Malloced<[T]> {
-    /// Constructs an instance for a slice from a pointer and a length.
+    /// Copies the value into an owned
+    /// [`Cow`](https://doc.rust-lang.org/std/borrow/enum.Cow.html) and frees
+    /// the `malloc`-ed memory.
     ///
-    /// # Safety
-    ///
-    /// Behavior is undefined if any of the following conditions are violated:
+    /// This is useful for passing `malloc`-ed data to APIs that accept `Cow`,
+    /// such as turning a `Malloced<str>` into a `Cow<'static, str>`. This is
+    /// also available via
+    /// [`From`](https://doc.rust-lang.org/std/convert/trait.From.html).
     ///
-    /// - `data` must have been allocated by `malloc` so that it can be `free`-d
-    ///   on [`Drop`](https://doc.rust-lang.org/std/ops/trait.Drop.html).
+    /// This requires the `std` feature.
     ///
-    /// - `data` must be
-    ///   [valid](https://doc.rust-lang.org/std/ptr/index.html#safety) for both
-    ///   reads and writes for `len * mem::size_of::<T>()` many bytes, and it
-    ///   must be properly aligned. This means in particular:
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::into_cow(this)` instead of `this.into_cow()`.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn into_cow<'a>(this: Self) -> std::borrow::Cow<'a, T>
+    where
+        T: ToOwned + 'a,
+    {
+        std::borrow::Cow::Owned(T::to_owned(&this))
+    }
+}
+
+impl<T> Malloced<T> {
+    /// Allocates memory with `malloc` and moves `value` into it.
     ///
-    ///     - The entire memory range of this slice must be contained within a
-    ///       single allocated object! Slices can never span across multiple
-    ///       allocated objects.
+    /// Panics if `malloc` fails or does not return memory aligned for `T`.
+    #[inline]
+    pub(crate) fn from_value(value: T) -> Self {
+        let buf = malloc_array::<T>(1);
+        unsafe {
+            buf.as_ptr().write(value);
+            Malloced::from_non_null(buf)
+        }
+    }
+
+    /// Moves the value out and frees its memory.
     ///
-    ///     - `data` must be non-null and aligned even for zero-length slices.
-    ///       One reason for this is that enum layout optimizations may rely on
-    ///       references (including slices of any length) being aligned and
-    ///       non-null to distinguish them from other data. You can obtain a
-    ///       pointer that is usable as `data` for zero-length slices using
-    ///       [`NonNull::dangling()`](https://doc.rust-lang.org/std/ptr/struct.NonNull.html#method.dangling).
+    /// This avoids pairing
+    /// [`ptr::read`](https://doc.rust-lang.org/std/ptr/fn.read.html) with
+    /// `free` by hand. Zero-sized values are handled the same way, so their
+    /// memory is freed exactly once.
     ///
-    /// - `data` must point to `len` consecutive properly initialized values of
-    ///   type `T`.
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::into_inner(this)` instead of `this.into_inner()`.
     ///
-    /// - The total size `len * mem::size_of::<T>()` of the slice must be no
-    ///   larger than `isize::MAX`. See the safety documentation of
-    ///   [`pointer::offset`](https://doc.rust-lang.org/std/primitive.pointer.html#method.offset).
+    /// # Examples
     ///
-    /// See
-    /// [`slice::from_raw_parts_mut`](https://doc.rust-lang.org/std/slice/fn.from_raw_parts_mut.html)
-    /// for details.
+    /// ```
+    /// # use malloced::Malloced;
+    /// let m = unsafe { Malloced::<u32>::new_in_place(|out| out.write(7)) };
+    /// assert_eq!(Malloced::into_inner(m), 7);
+    /// ```
     #[inline]
-    pub unsafe fn slice_from_raw_parts(data: *mut T, len: usize) -> Self {
-        Self::from_raw(core::ptr::slice_from_raw_parts_mut(data, len))
+    pub fn into_inner(this: Self) -> T {
+        let ptr = ManuallyDrop::new(this).ptr.as_ptr();
+        unsafe {
+            let value = ptr.read();
+            sys::free(ptr as *mut c_void);
+            value
+        }
     }
-}
 
-impl Malloced<CStr> {
-    /// Wraps a raw `malloc`ed C string with a safe owned C string wrapper.
+    /// Allocates uninitialized memory for `T` with `malloc` and calls `init`
+    /// to initialize it in place.
+    ///
+    /// This fits C APIs shaped like `void init(T *out)`, which write a value
+    /// into caller-provided memory.
+    ///
+    /// If `init` panics, the memory is freed without dropping its contents.
     ///
     /// # Safety
     ///
-    /// See [`CStr::from_ptr` safety docs](CStr::from_ptr).
-    #[inline]
-    pub unsafe fn from_ptr(ptr: *mut c_char) -> Self {
-        // If `&CStr` is a thin pointer, use a dummy length that is discarded.
-        let len = if mem::size_of::<*mut CStr>() == mem::size_of::<*mut c_char>() {
-            1
-        } else {
-            CStr::from_ptr(ptr).to_bytes_with_nul().len()
-        };
+    /// `init` must fully initialize the value behind the pointer before it
+    /// returns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails or does not return memory aligned for `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// #[repr(C)]
+    /// struct Point {
+    ///     x: i32,
+    ///     y: i32,
+    /// }
+    ///
+    /// let point = unsafe {
+    ///     Malloced::<Point>::new_in_place(|out| out.write(Point { x: 1, y: 2 }))
+    /// };
+    /// assert_eq!((point.x, point.y), (1, 2));
+    /// ```
+    pub unsafe fn new_in_place<F>(init: F) -> Self
+    where
+        F: FnOnce(*mut T),
+    {
+        let buf = malloc_array::<T>(1);
+        let free = FreeGuard(buf.as_ptr() as *mut c_void);
 
-        let ptr = core::ptr::slice_from_raw_parts_mut(ptr, len) as *mut CStr;
+        init(buf.as_ptr());
 
-        Self::from_raw(ptr)
+        mem::forget(free);
+        Malloced::from_non_null(buf)
     }
-}
 
-impl Malloced<dyn Any> {
-    /// Attempt to downcast the instance to a concrete type.
-    #[inline]
-    pub fn downcast<T: Any>(self) -> Result<Malloced<T>, Self> {
-        if self.is::<T>() {
-            let raw: *mut dyn Any = Malloced::into_raw(self);
-            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
-        } else {
+    /// Converts the contained value with the fallible function `f`.
+    ///
+    /// If `U` fits within the size and alignment of the existing allocation,
+    /// it is reused. Otherwise, a new allocation is made for `U` and the old
+    /// one is freed.
+    ///
+    /// If `f` returns an error, the value has been consumed by `f` and the
+    /// memory is freed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a new allocation is needed and `malloc` fails.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::try_map(this, f)` instead of `this.try_map(f)`.
+    pub fn try_map<U, E, F>(this: Self, f: F) -> Result<Malloced<U>, E>
+    where
+        F: FnOnce(T) -> Result<U, E>,
+    {
+        let ptr = ManuallyDrop::new(this).ptr.as_ptr();
+        // Frees the memory if `f` fails or panics.
+        let free = FreeGuard(ptr as *mut c_void);
+
+        let value = f(unsafe { ptr.read() })?;
+
+        let fits =
+            mem::size_of::<U>() <= mem::size_of::<T>() && ptr as usize % mem::align_of::<U>() == 0;
+
+        if fits {
+            mem::forget(free);
+            let ptr = ptr as *mut U;
+            unsafe {
+                ptr.write(value);
+                Ok(Malloced::from_raw(ptr))
+            }
+        } else {
+            drop(free);
+            Ok(Malloced::from_value(value))
+        }
+    }
+
+    /// Replaces the contained value with `value`, returning the old value.
+    ///
+    /// This does not reallocate. It is equivalent to
+    /// `mem::replace(&mut *this, value)`, but cannot be shadowed by a method on
+    /// `T`.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::replace(this, value)` instead of `this.replace(value)`.
+    #[inline]
+    pub fn replace(this: &mut Self, value: T) -> T {
+        mem::replace(&mut **this, value)
+    }
+
+    /// Takes the contained value, leaving [`Default::default()`] in its place.
+    ///
+    /// This does not reallocate. It is equivalent to
+    /// `mem::take(&mut *this)`, but cannot be shadowed by a method on `T`.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::take(this)` instead of `this.take()`.
+    #[inline]
+    pub fn take(this: &mut Self) -> T
+    where
+        T: Default,
+    {
+        mem::take(&mut **this)
+    }
+}
+
+impl<T> Malloced<ManuallyDrop<T>> {
+    /// Allocates memory with `malloc` and moves `value` into it without
+    /// scheduling its destructor.
+    ///
+    /// Dropping the result only `free`s the memory. The destructor of `T` runs
+    /// only if [`Malloced::drop_inner`](#method.drop_inner) is called, which
+    /// lets it run at a controlled moment separate from the `free`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails or does not return memory aligned for `T`.
+    #[inline]
+    pub fn new_manually_drop(value: T) -> Self {
+        Malloced::from_value(ManuallyDrop::new(value))
+    }
+
+    /// Runs the destructor of the contained value without freeing its memory.
+    ///
+    /// The memory is still freed when `this` is dropped, which never runs the
+    /// destructor of `T` again.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::drop_inner(this)` instead of `this.drop_inner()`.
+    ///
+    /// # Safety
+    ///
+    /// This must be called at most once, and the contained value must not be
+    /// used afterward. See
+    /// [`ManuallyDrop::drop`](https://doc.rust-lang.org/std/mem/struct.ManuallyDrop.html#method.drop)
+    /// for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let mut m = Malloced::new_manually_drop(vec![1, 2, 3]);
+    /// assert_eq!(m.len(), 3);
+    ///
+    /// // Release the vector's buffer now; `m` is freed later.
+    /// unsafe { Malloced::drop_inner(&mut m) };
+    /// ```
+    #[inline]
+    pub unsafe fn drop_inner(this: &mut Self) {
+        ManuallyDrop::drop(&mut **this);
+    }
+}
+
+impl<T> Malloced<[T]> {
+    /// Constructs an instance for a slice from a pointer and a length.
+    ///
+    /// # Safety
+    ///
+    /// Behavior is undefined if any of the following conditions are violated:
+    ///
+    /// - `data` must have been allocated by `malloc` so that it can be `free`-d
+    ///   on [`Drop`](https://doc.rust-lang.org/std/ops/trait.Drop.html).
+    ///
+    /// - `data` must be
+    ///   [valid](https://doc.rust-lang.org/std/ptr/index.html#safety) for both
+    ///   reads and writes for `len * mem::size_of::<T>()` many bytes, and it
+    ///   must be properly aligned. This means in particular:
+    ///
+    ///     - The entire memory range of this slice must be contained within a
+    ///       single allocated object! Slices can never span across multiple
+    ///       allocated objects.
+    ///
+    ///     - `data` must be non-null and aligned even for zero-length slices.
+    ///       One reason for this is that enum layout optimizations may rely on
+    ///       references (including slices of any length) being aligned and
+    ///       non-null to distinguish them from other data. You can obtain a
+    ///       pointer that is usable as `data` for zero-length slices using
+    ///       [`NonNull::dangling()`](https://doc.rust-lang.org/std/ptr/struct.NonNull.html#method.dangling).
+    ///
+    /// - `data` must point to `len` consecutive properly initialized values of
+    ///   type `T`.
+    ///
+    /// - The total size `len * mem::size_of::<T>()` of the slice must be no
+    ///   larger than `isize::MAX`. See the safety documentation of
+    ///   [`pointer::offset`](https://doc.rust-lang.org/std/primitive.pointer.html#method.offset).
+    ///
+    /// See
+    /// [`slice::from_raw_parts_mut`](https://doc.rust-lang.org/std/slice/fn.from_raw_parts_mut.html)
+    /// for details.
+    #[inline]
+    pub unsafe fn slice_from_raw_parts(data: *mut T, len: usize) -> Self {
+        Self::from_raw(core::ptr::slice_from_raw_parts_mut(data, len))
+    }
+
+    /// Allocates an uninitialized slice of `len` elements with `malloc`.
+    ///
+    /// The contents can be initialized with
+    /// [`Malloced::copy_from_slice`](#method.copy_from_slice) and then
+    /// converted with [`Malloced::assume_init`](#method.assume_init).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation size overflows, if `malloc` fails, or if
+    /// `malloc` does not return memory aligned for `T`.
+    #[inline]
+    pub fn new_uninit_slice(len: usize) -> Malloced<[MaybeUninit<T>]> {
+        let buf = malloc_array::<MaybeUninit<T>>(len);
+        unsafe { Malloced::slice_from_raw_parts(buf.as_ptr(), len) }
+    }
+
+    /// Allocates a slice of `len` elements, initializing each with `f(index)`.
+    ///
+    /// This mirrors
+    /// [`array::from_fn`](https://doc.rust-lang.org/std/array/fn.from_fn.html)
+    /// for a length known only at runtime.
+    ///
+    /// If `f` panics, the elements initialized so far are dropped and the
+    /// memory is freed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation size overflows, if `malloc` fails, or if
+    /// `malloc` does not return memory aligned for `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let squares = Malloced::<[usize]>::from_fn(4, |i| i * i);
+    /// assert_eq!(&*squares, &[0, 1, 4, 9]);
+    /// ```
+    pub fn from_fn<F>(len: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        struct Guard<T> {
+            buf: NonNull<T>,
+            init: usize,
+        }
+
+        impl<T> Drop for Guard<T> {
+            fn drop(&mut self) {
+                unsafe {
+                    let init = core::ptr::slice_from_raw_parts_mut(self.buf.as_ptr(), self.init);
+                    core::ptr::drop_in_place(init);
+                    sys::free(self.buf.as_ptr() as *mut c_void);
+                }
+            }
+        }
+
+        let mut guard = Guard {
+            buf: malloc_array::<T>(len),
+            init: 0,
+        };
+
+        while guard.init < len {
+            unsafe { guard.buf.as_ptr().add(guard.init).write(f(guard.init)) };
+            guard.init += 1;
+        }
+
+        let guard = ManuallyDrop::new(guard);
+        unsafe { Self::slice_from_raw_parts(guard.buf.as_ptr(), len) }
+    }
+
+    /// Allocates uninitialized memory for `len` elements with `malloc` and
+    /// calls `init` with a pointer to it and `len` to initialize them in place.
+    ///
+    /// This fits C APIs shaped like `void fill(T *buf, size_t n)`, which write
+    /// values into caller-provided memory.
+    ///
+    /// If `init` panics, the memory is freed without dropping its contents.
+    ///
+    /// # Safety
+    ///
+    /// `init` must initialize all `len` elements before it returns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation size overflows, if `malloc` fails, or if
+    /// `malloc` does not return memory aligned for `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let buf = unsafe {
+    ///     Malloced::<[u8]>::new_slice_in_place(4, |ptr, len| ptr.write_bytes(7, len))
+    /// };
+    /// assert_eq!(&*buf, &[7; 4]);
+    /// ```
+    pub unsafe fn new_slice_in_place<F>(len: usize, init: F) -> Self
+    where
+        F: FnOnce(*mut T, usize),
+    {
+        let buf = malloc_array::<T>(len);
+        let free = FreeGuard(buf.as_ptr() as *mut c_void);
+
+        init(buf.as_ptr(), len);
+
+        mem::forget(free);
+        Self::slice_from_raw_parts(buf.as_ptr(), len)
+    }
+
+    /// Allocates a slice of `len` clones of `value`.
+    ///
+    /// If `clone` panics, the elements cloned so far are dropped and the memory
+    /// is freed.
+    ///
+    /// Only with the `nightly` feature are byte types such as `u8` instead
+    /// filled with a single `memset`. Stable builds clone every element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation size overflows, if `malloc` fails, or if
+    /// `malloc` does not return memory aligned for `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let buf = Malloced::<[u8]>::repeat(&0xff, 4);
+    /// assert_eq!(&*buf, &[0xff; 4]);
+    /// ```
+    #[inline]
+    pub fn repeat(value: &T, len: usize) -> Self
+    where
+        T: Clone,
+    {
+        #[cfg(feature = "nightly")]
+        return nightly::SpecRepeat::spec_repeat(value, len);
+
+        #[cfg(not(feature = "nightly"))]
+        return Self::from_fn(len, |_| value.clone());
+    }
+
+    /// Overwrites the elements of `target` with clones of `source`.
+    ///
+    /// If the lengths are equal, the existing allocation is reused and each
+    /// element is updated via [`Clone::clone_from`]. Otherwise, a new
+    /// allocation is made and the old one is freed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a new allocation is needed and `malloc` fails.
+    pub fn clone_slice_into(source: &[T], target: &mut Self)
+    where
+        T: Clone,
+    {
+        if source.len() == target.len() {
+            target.clone_from_slice(source);
+        } else {
+            *target = Self::from_fn(source.len(), |i| source[i].clone());
+        }
+    }
+
+    /// Consumes the instance, returning the raw data pointer and the number of
+    /// elements.
+    ///
+    /// This is the inverse of
+    /// [`Malloced::slice_from_raw_parts`](#method.slice_from_raw_parts), and
+    /// is useful for C APIs that take a pointer and length separately. The
+    /// memory is not freed, so it is the caller's responsibility to eventually
+    /// `free` it.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::into_raw_parts(this)` instead of `this.into_raw_parts()`.
+    ///
+    /// # Examples
+    ///
+    /// The parts can be passed to C and later re-imported to run destructors
+    /// and `free` the memory:
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let mut buf = Malloced::<[u8]>::new_uninit_slice(3);
+    /// Malloced::copy_from_slice(&mut buf, &[1, 2, 3]);
+    /// let buf = unsafe { Malloced::assume_init(buf) };
+    ///
+    /// let (ptr, len) = Malloced::into_raw_parts(buf);
+    ///
+    /// // ... pass `ptr` and `len` to C ...
+    ///
+    /// let buf = unsafe { Malloced::slice_from_raw_parts(ptr, len) };
+    /// assert_eq!(&*buf, &[1, 2, 3]);
+    /// ```
+    #[inline]
+    pub fn into_raw_parts(this: Self) -> (*mut T, usize) {
+        let len = this.len();
+        let ptr = Self::into_raw(this) as *mut T;
+        (ptr, len)
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> Malloced<[T]> {
+    /// Reinterprets the slice's allocation as a slice of another
+    /// [`Pod`](bytemuck::Pod) type without copying.
+    ///
+    /// This requires the `bytemuck` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`bytemuck::cast_slice`]: if the
+    /// byte length of the slice is not a multiple of `size_of::<U>()`, or if
+    /// the data is not sufficiently aligned for `U`.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::cast_slice(this)` instead of `this.cast_slice()`.
+    #[inline]
+    pub fn cast_slice<U: bytemuck::Pod>(this: Self) -> Malloced<[U]> {
+        let len = match bytemuck::try_cast_slice::<T, U>(&this) {
+            Ok(slice) => slice.len(),
+            Err(error) => panic!("cast_slice>{:?}", error),
+        };
+
+        let (ptr, _) = Self::into_raw_parts(this);
+        unsafe { Malloced::slice_from_raw_parts(ptr as *mut U, len) }
+    }
+}
+
+impl Malloced<[u8]> {
+    /// Returns `true` if the bytes are equal to `other`, comparing them with
+    /// the C library's `memcmp`.
+    ///
+    /// This only exists for byte slices. `==` remains available for all
+    /// element types, and for bytes the standard library already lowers it to
+    /// a `memcmp`-like comparison, so this mainly guarantees that the platform
+    /// `memcmp` is used.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::bytes_eq(this, other)` instead of
+    /// `this.bytes_eq(other)`.
+    #[inline]
+    pub fn bytes_eq(this: &Self, other: &[u8]) -> bool {
+        let len = this.len();
+        if len != other.len() {
+            return false;
+        }
+        if len == 0 {
+            return true;
+        }
+        unsafe { sys::memcmp(this.as_ptr().cast(), other.as_ptr().cast(), len) == 0 }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Malloced<[u8]> {
+    /// Allocates `len` bytes with `malloc` and fills them by reading exactly
+    /// `len` bytes from `reader`.
+    ///
+    /// This requires the `std` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from [`Read::read_exact`](std::io::Read::read_exact),
+    /// in which case the buffer is freed. In particular, if `reader` reaches
+    /// end-of-file before `len` bytes are read, an error of kind
+    /// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails to allocate memory.
+    pub fn from_reader<R: std::io::Read + ?Sized>(
+        reader: &mut R,
+        len: usize,
+    ) -> std::io::Result<Self> {
+        let mut buf = Self::new_uninit_slice(len);
+
+        // `Read` implementations may read from the buffer, so it must be
+        // initialized first.
+        let mut buf = unsafe {
+            core::ptr::write_bytes(Malloced::as_mut_ptr(&mut buf) as *mut u8, 0, len);
+            Malloced::assume_init(buf)
+        };
+
+        reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+impl<T, const N: usize> Malloced<[T; N]> {
+    /// Converts the array into a slice of length `N`.
+    ///
+    /// This neither allocates nor copies, since the pointer is reused.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::into_slice(this)` instead of `this.into_slice()`.
+    #[inline]
+    pub fn into_slice(this: Self) -> Malloced<[T]> {
+        let ptr = Malloced::into_raw(this) as *mut T;
+        unsafe { Malloced::slice_from_raw_parts(ptr, N) }
+    }
+}
+
+impl<T> Malloced<[MaybeUninit<T>]> {
+    /// Initializes the slice by copying all elements from `src`.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::copy_from_slice(this, src)` instead of
+    /// `this.copy_from_slice(src)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` has a different length than `this`.
+    #[inline]
+    pub fn copy_from_slice(this: &mut Self, src: &[T])
+    where
+        T: Copy,
+    {
+        assert_eq!(
+            this.len(),
+            src.len(),
+            "source slice length does not match destination slice length",
+        );
+
+        unsafe {
+            let dst = Malloced::as_mut_ptr(this) as *mut T;
+            core::ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len());
+        }
+    }
+
+    /// Converts to `Malloced<[T]>`.
+    ///
+    /// # Safety
+    ///
+    /// All elements must be initialized. See
+    /// [`MaybeUninit::assume_init`](https://doc.rust-lang.org/std/mem/union.MaybeUninit.html#method.assume_init).
+    #[inline]
+    pub unsafe fn assume_init(this: Self) -> Malloced<[T]> {
+        let (ptr, len) = Malloced::into_raw_parts(this);
+        Malloced::slice_from_raw_parts(ptr as *mut T, len)
+    }
+}
+
+impl Malloced<str> {
+    /// Constructs an instance for a string slice from a pointer and a length
+    /// in bytes.
+    ///
+    /// This is useful for C APIs that return a pointer and a length for a
+    /// UTF-8 buffer that is not nul-terminated.
+    ///
+    /// # Safety
+    ///
+    /// Behavior is undefined if any of the following conditions are violated:
+    ///
+    /// - All of the safety requirements of
+    ///   [`Malloced::slice_from_raw_parts`](#method.slice_from_raw_parts) for
+    ///   `data` and `len` as a `[u8]`.
+    ///
+    /// - The `len` bytes at `data` must be valid UTF-8. See
+    ///   [`str::from_utf8_unchecked`](https://doc.rust-lang.org/std/str/fn.from_utf8_unchecked.html)
+    ///   for details.
+    #[inline]
+    pub unsafe fn from_raw_parts(data: *mut u8, len: usize) -> Self {
+        Self::from_raw(core::ptr::slice_from_raw_parts_mut(data, len) as *mut str)
+    }
+
+    /// Converts the string to its ASCII upper case equivalent in place.
+    ///
+    /// ASCII letters 'a' to 'z' are mapped to 'A' to 'Z', but non-ASCII
+    /// letters are unchanged. This never reallocates, since the byte length
+    /// stays the same.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::make_ascii_uppercase(this)` instead of
+    /// `this.make_ascii_uppercase()`.
+    #[inline]
+    pub fn make_ascii_uppercase(this: &mut Self) {
+        str::make_ascii_uppercase(this);
+    }
+
+    /// Converts the string to its ASCII lower case equivalent in place.
+    ///
+    /// ASCII letters 'A' to 'Z' are mapped to 'a' to 'z', but non-ASCII
+    /// letters are unchanged. This never reallocates, since the byte length
+    /// stays the same.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::make_ascii_lowercase(this)` instead of
+    /// `this.make_ascii_lowercase()`.
+    #[inline]
+    pub fn make_ascii_lowercase(this: &mut Self) {
+        str::make_ascii_lowercase(this);
+    }
+}
+
+impl Malloced<CStr> {
+    /// Wraps a raw `malloc`ed C string with a safe owned C string wrapper.
+    ///
+    /// # Safety
+    ///
+    /// See [`CStr::from_ptr` safety docs](CStr::from_ptr).
+    #[inline]
+    pub unsafe fn from_ptr(ptr: *mut c_char) -> Self {
+        // If `&CStr` is a thin pointer, use a dummy length that is discarded.
+        let len = if mem::size_of::<*mut CStr>() == mem::size_of::<*mut c_char>() {
+            1
+        } else {
+            CStr::from_ptr(ptr).to_bytes_with_nul().len()
+        };
+
+        let ptr = core::ptr::slice_from_raw_parts_mut(ptr, len) as *mut CStr;
+
+        Self::from_raw(ptr)
+    }
+
+    /// Wraps a raw `malloc`ed C string with a safe owned C string wrapper,
+    /// returning `None` if `ptr` is null.
+    ///
+    /// # Safety
+    ///
+    /// If `ptr` is non-null, see
+    /// [`CStr::from_ptr` safety docs](CStr::from_ptr).
+    #[inline]
+    pub unsafe fn try_from_ptr(ptr: *mut c_char) -> Option<Self> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Self::from_ptr(ptr))
+        }
+    }
+
+    /// Returns the length of the C string in bytes, excluding the nul
+    /// terminator.
+    ///
+    /// If `*mut CStr` is a fat pointer, this reads the stored length in O(1)
+    /// rather than scanning for the nul terminator like
+    /// [`CStr::to_bytes`] may do.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::count_bytes(this)` instead of `this.count_bytes()`.
+    #[inline]
+    pub fn count_bytes(this: &Self) -> usize {
+        if mem::size_of::<*mut CStr>() == mem::size_of::<*mut c_char>() {
+            this.to_bytes().len()
+        } else {
+            // SAFETY: The fat pointer's metadata is the length of the string
+            // including the nul terminator, and all of those bytes are valid.
+            let bytes = unsafe { &*(this.ptr.as_ptr() as *const [u8]) };
+            bytes.len() - 1
+        }
+    }
+
+    /// Returns an iterator over the bytes of the string, not including the nul
+    /// terminator.
+    ///
+    /// This is equivalent to iterating over [`CStr::to_bytes`], which is O(1)
+    /// since the length is stored in the fat pointer.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::bytes(this)` instead of `this.bytes()`.
+    #[inline]
+    pub fn bytes(this: &Self) -> impl Iterator<Item = u8> + '_ {
+        this.to_bytes().iter().copied()
+    }
+
+    /// Copies a byte slice with a trailing nul byte into a new `malloc`-ed C
+    /// string.
+    ///
+    /// This is the safe counterpart to
+    /// [`Malloced::from_ptr`](#method.from_ptr), and has the same requirements
+    /// as [`CStr::from_bytes_with_nul`]: `bytes` must end with a nul byte and
+    /// must not contain any interior nul bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails to allocate memory.
+    pub fn from_bytes_with_nul(bytes: &[u8]) -> Result<Self, FromBytesWithNulError> {
+        let bytes = CStr::from_bytes_with_nul(bytes)?.to_bytes_with_nul();
+
+        unsafe {
+            let buf = malloc_or_panic(bytes.len());
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), buf, bytes.len());
+
+            let ptr = core::ptr::slice_from_raw_parts_mut(buf, bytes.len()) as *mut CStr;
+            Ok(Self::from_raw(ptr))
+        }
+    }
+}
+
+impl Malloced<dyn Any> {
+    /// Attempt to downcast the instance to a concrete type.
+    #[inline]
+    pub fn downcast<T: Any>(self) -> Result<Malloced<T>, Self> {
+        if self.is::<T>() {
+            let raw: *mut dyn Any = Malloced::into_raw(self);
+            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl Malloced<dyn Any + Send> {
+    /// Attempt to downcast the instance to a concrete type.
+    #[inline]
+    pub fn downcast<T: Any>(self) -> Result<Malloced<T>, Self> {
+        if self.is::<T>() {
+            let raw: *mut (dyn Any + Send) = Malloced::into_raw(self);
+            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl Malloced<dyn Any + Send + Sync> {
+    /// Attempt to downcast the instance to a concrete type.
+    #[inline]
+    pub fn downcast<T: Any>(self) -> Result<Malloced<T>, Self> {
+        if self.is::<T>() {
+            let raw: *mut (dyn Any + Send + Sync) = Malloced::into_raw(self);
+            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Malloced<dyn Error> {
+    /// Attempt to downcast the instance to a concrete error type.
+    ///
+    /// Use
+    /// [`is`](https://doc.rust-lang.org/std/error/trait.Error.html#method.is)
+    /// or
+    /// [`downcast_ref`](https://doc.rust-lang.org/std/error/trait.Error.html#method.downcast_ref)
+    /// through [`Deref`] to check or borrow the concrete type without
+    /// consuming the instance.
+    ///
+    /// This requires the `std` feature.
+    ///
+    /// [`Deref`]: core::ops::Deref
+    #[inline]
+    pub fn downcast<T: Error + 'static>(self) -> Result<Malloced<T>, Self> {
+        if self.is::<T>() {
+            let raw: *mut dyn Error = Malloced::into_raw(self);
+            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Malloced<dyn Error + Send> {
+    /// Attempt to downcast the instance to a concrete error type.
+    ///
+    /// Use
+    /// [`is`](https://doc.rust-lang.org/std/error/trait.Error.html#method.is)
+    /// or
+    /// [`downcast_ref`](https://doc.rust-lang.org/std/error/trait.Error.html#method.downcast_ref)
+    /// through [`Deref`] to check or borrow the concrete type without
+    /// consuming the instance.
+    ///
+    /// This requires the `std` feature.
+    ///
+    /// [`Deref`]: core::ops::Deref
+    #[inline]
+    pub fn downcast<T: Error + 'static>(self) -> Result<Malloced<T>, Self> {
+        if self.is::<T>() {
+            let raw: *mut (dyn Error + Send) = Malloced::into_raw(self);
+            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Malloced<dyn Error + Send + Sync> {
+    /// Attempt to downcast the instance to a concrete error type.
+    ///
+    /// Use
+    /// [`is`](https://doc.rust-lang.org/std/error/trait.Error.html#method.is)
+    /// or
+    /// [`downcast_ref`](https://doc.rust-lang.org/std/error/trait.Error.html#method.downcast_ref)
+    /// through [`Deref`] to check or borrow the concrete type without
+    /// consuming the instance.
+    ///
+    /// This requires the `std` feature.
+    ///
+    /// [`Deref`]: core::ops::Deref
+    #[inline]
+    pub fn downcast<T: Error + 'static>(self) -> Result<Malloced<T>, Self> {
+        if self.is::<T>() {
+            let raw: *mut (dyn Error + Send + Sync) = Malloced::into_raw(self);
+            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
+        } else {
             Err(self)
         }
     }
-}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the number of times it is dropped.
+    #[derive(Clone, Debug)]
+    pub(crate) struct DropCounter<'a>(pub(crate) &'a core::cell::Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod error {
+        use super::*;
+        use std::fmt;
+
+        #[derive(Debug)]
+        struct Inner;
+
+        impl fmt::Display for Inner {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("inner")
+            }
+        }
+
+        impl Error for Inner {}
+
+        #[derive(Debug)]
+        struct Outer(Inner);
+
+        impl fmt::Display for Outer {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("outer")
+            }
+        }
+
+        impl Error for Outer {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        #[test]
+        fn source() {
+            let error: Malloced<dyn Error> = Malloced::alloc_one(Outer(Inner)).unwrap().into();
+            assert_eq!(error.to_string(), "outer");
+            assert_eq!(error.source().unwrap().to_string(), "inner");
+        }
+
+        #[test]
+        fn question_mark() {
+            fn fails() -> Result<(), Malloced<dyn Error + Send + Sync>> {
+                Err(Malloced::alloc_one(Inner).unwrap())?
+            }
+
+            fn boxed() -> Result<(), Box<dyn Error>> {
+                fails()?;
+                Ok(())
+            }
+
+            assert_eq!(boxed().unwrap_err().to_string(), "inner");
+        }
+
+        #[test]
+        fn downcast() {
+            let error: Malloced<dyn Error + Send + Sync> =
+                Malloced::alloc_one(Inner).unwrap().into();
+            assert!(error.is::<Inner>());
+            assert!(!error.is::<Outer>());
+            assert!(error.downcast_ref::<Inner>().is_some());
+            assert!(error.downcast_ref::<Outer>().is_none());
+
+            let error = error.downcast::<Outer>().unwrap_err();
+            let inner: Malloced<Inner> = error.downcast::<Inner>().unwrap();
+            assert_eq!(inner.to_string(), "inner");
+
+            let error: Malloced<dyn Error> = Malloced::alloc_one(Outer(Inner)).unwrap().into();
+            assert!(error.downcast::<Outer>().is_ok());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod any {
+        use super::*;
+
+        #[test]
+        fn into_vec() {
+            let values: Vec<Malloced<dyn Any>> = vec![
+                Malloced::alloc_one(1u8).unwrap().into(),
+                Malloced::alloc_one("two").unwrap().into(),
+                Malloced::alloc_one([3u32; 3]).unwrap().into(),
+            ];
+
+            assert_eq!(values[0].downcast_ref::<u8>(), Some(&1));
+            assert_eq!(values[1].downcast_ref::<&str>(), Some(&"two"));
+            assert_eq!(values[2].downcast_ref::<[u32; 3]>(), Some(&[3; 3]));
+            assert!(values[0].downcast_ref::<u16>().is_none());
+
+            let mut values = values.into_iter();
+            let value: Malloced<u8> = values.next().unwrap().downcast().unwrap();
+            assert_eq!(*value, 1);
+            assert!(values.next().unwrap().downcast::<u8>().is_err());
+        }
+
+        #[test]
+        fn send_sync() {
+            let send: Malloced<dyn Any + Send> = Malloced::alloc_one(1u64).unwrap().into();
+            assert_eq!(*send.downcast::<u64>().unwrap(), 1);
+
+            let sync: Malloced<dyn Any + Send + Sync> = Malloced::alloc_one(2u64).unwrap().into();
+            assert_eq!(*sync.downcast::<u64>().unwrap(), 2);
+        }
+    }
+
+    mod into_raw {
+        use super::*;
+        use core::cell::Cell;
+
+        #[test]
+        fn with_drop() {
+            let drops = Cell::new(0);
+            let value = Malloced::alloc_one(DropCounter(&drops)).unwrap();
+
+            let (ptr, drop_fn) = Malloced::into_raw_with_drop(value);
+            assert_eq!(drops.get(), 0);
+
+            // Simulate C taking ownership and later releasing it.
+            unsafe { drop_fn(ptr as *mut c_void) };
+            assert_eq!(drops.get(), 1);
+
+            unsafe { drop_fn(core::ptr::null_mut()) };
+            assert_eq!(drops.get(), 1);
+        }
+
+        #[test]
+        fn c_void() {
+            let mut value = Malloced::alloc_one(7u64).unwrap();
+            let ptr = Malloced::as_ptr(&value) as *const c_void;
+            assert_eq!(Malloced::as_c_void(&value), ptr);
+            assert_eq!(Malloced::as_mut_c_void(&mut value) as *const c_void, ptr);
+
+            let mut buf = Malloced::<[u16]>::alloc(&[1, 2, 3]).unwrap();
+            let ptr = Malloced::as_ptr(&buf) as *const c_void;
+            assert_eq!(Malloced::as_c_void(&buf), ptr);
+            assert_eq!(Malloced::as_mut_c_void(&mut buf) as *const c_void, ptr);
+        }
+    }
+
+    mod leak {
+        use super::*;
+        use core::marker::PhantomPinned;
+
+        struct Pinned {
+            value: u32,
+            _pin: PhantomPinned,
+        }
+
+        #[test]
+        fn pinned() {
+            let value = Malloced::alloc_one(Pinned {
+                value: 1,
+                _pin: PhantomPinned,
+            })
+            .unwrap();
+            let ptr = Malloced::as_ptr(&value);
+
+            let mut leaked: Pin<&'static mut Pinned> = Malloced::leak_pinned(value.into());
+            assert_eq!(&*leaked as *const Pinned, ptr);
+            assert_eq!(leaked.value, 1);
+
+            unsafe { leaked.as_mut().get_unchecked_mut().value = 2 };
+            assert_eq!(leaked.value, 2);
+
+            // Free the memory to keep leak checkers quiet.
+            let ptr = unsafe { leaked.get_unchecked_mut() as *mut Pinned };
+            drop(unsafe { Malloced::from_raw(ptr) });
+        }
+    }
+
+    mod new_in_place {
+        use super::*;
+
+        #[derive(Debug, PartialEq)]
+        #[repr(C)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        #[test]
+        fn init() {
+            // Stands in for a C function shaped like `void init(Point *out)`.
+            unsafe fn point_init(out: *mut Point) {
+                (*out).x = 3;
+                (*out).y = 4;
+            }
+
+            let point = unsafe { Malloced::new_in_place(|out| point_init(out)) };
+            assert_eq!(*point, Point { x: 3, y: 4 });
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn panic() {
+            use std::panic::{self, AssertUnwindSafe};
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+                Malloced::<Point>::new_in_place(|_| panic!("fail"))
+            }));
+            assert!(result.is_err());
+        }
+    }
+
+    mod str {
+        use super::*;
+
+        #[test]
+        fn from_raw_parts() {
+            let bytes = "héllo".as_bytes();
+            let (ptr, len) = Malloced::into_raw_parts(Malloced::<[u8]>::alloc(bytes).unwrap());
+
+            let s = unsafe { Malloced::<str>::from_raw_parts(ptr, len) };
+            assert_eq!(&*s, "héllo");
+            assert_eq!(s.len(), bytes.len());
+        }
+
+        #[test]
+        fn make_ascii_case() {
+            let mut s = Malloced::alloc_str("Hello, Wörld!").unwrap();
+            let ptr = Malloced::as_ptr(&s) as *const u8;
+
+            Malloced::make_ascii_uppercase(&mut s);
+            assert_eq!(&*s, "HELLO, WöRLD!");
+            assert_eq!(Malloced::as_ptr(&s) as *const u8, ptr);
+
+            Malloced::make_ascii_lowercase(&mut s);
+            assert_eq!(&*s, "hello, wörld!");
+            assert_eq!(Malloced::as_ptr(&s) as *const u8, ptr);
+        }
+    }
+
+    mod into_inner {
+        use super::*;
+
+        #[test]
+        fn value() {
+            let m = unsafe { Malloced::<[u64; 2]>::new_in_place(|out| out.write([1, 2])) };
+            assert_eq!(Malloced::into_inner(m), [1, 2]);
+        }
+
+        #[test]
+        fn zst() {
+            let m = unsafe { Malloced::<()>::new_in_place(|_| {}) };
+            Malloced::into_inner(m);
+        }
+
+        #[test]
+        fn no_drop() {
+            use core::cell::Cell;
+
+            let drops = Cell::new(0);
+            let m = Malloced::alloc_one(DropCounter(&drops)).unwrap();
+
+            let value = Malloced::into_inner(m);
+            assert_eq!(drops.get(), 0);
+
+            drop(value);
+            assert_eq!(drops.get(), 1);
+        }
+    }
+
+    mod manually_drop {
+        use super::*;
+        use core::cell::Cell;
+
+        #[test]
+        fn drop_inner() {
+            let drops = Cell::new(0);
+            let mut m = Malloced::new_manually_drop(DropCounter(&drops));
+            assert_eq!(drops.get(), 0);
+
+            unsafe { Malloced::drop_inner(&mut m) };
+            assert_eq!(drops.get(), 1);
+
+            // Freeing the memory does not run the destructor again.
+            drop(m);
+            assert_eq!(drops.get(), 1);
+        }
+
+        #[test]
+        fn no_drop() {
+            let drops = Cell::new(0);
+            drop(Malloced::new_manually_drop(DropCounter(&drops)));
+            assert_eq!(drops.get(), 0);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    mod collections {
+        use super::*;
+        use alloc::{
+            string::{String, ToString},
+            vec,
+            vec::Vec,
+        };
+        use core::cell::Cell;
+
+        #[test]
+        fn vec_round_trip() {
+            let buf = Malloced::<[String]>::from(vec!["a".to_string(), "bc".to_string()]);
+            assert_eq!(&*buf, &["a", "bc"]);
+
+            let vec = Vec::from(buf);
+            assert_eq!(vec, ["a", "bc"]);
+
+            let empty = Malloced::<[u8]>::from(Vec::new());
+            assert!(empty.is_empty());
+            assert!(Vec::from(empty).is_empty());
+
+            let units = Malloced::<[()]>::from(vec![(); 3]);
+            assert_eq!(Vec::from(units).len(), 3);
+        }
+
+        #[test]
+        fn vec_moves() {
+            let drops = Cell::new(0);
+            let vec: Vec<_> = (0..3).map(|i| (DropCounter(&drops), i)).collect();
+
+            let buf = Malloced::<[(DropCounter, u32)]>::from(vec);
+            assert_eq!(drops.get(), 0);
+
+            let vec = Vec::from(buf);
+            assert_eq!(drops.get(), 0);
+            assert_eq!(vec.iter().map(|d| d.1).collect::<Vec<_>>(), [0, 1, 2]);
+
+            drop(vec);
+            assert_eq!(drops.get(), 3);
+        }
+
+        #[test]
+        fn string_round_trip() {
+            let s = Malloced::<str>::from(String::from("héllo"));
+            assert_eq!(&*s, "héllo");
+
+            let s = String::from(s);
+            assert_eq!(s, "héllo");
+
+            assert_eq!(String::from(Malloced::<str>::from(String::new())), "");
+        }
+    }
+
+    mod bytes_eq {
+        use super::*;
+
+        #[test]
+        fn large() {
+            let a = Malloced::<[u8]>::from_fn(1 << 16, |i| (i * 7) as u8);
+            let mut b = Malloced::<[u8]>::from_fn(1 << 16, |i| (i * 7) as u8);
+
+            assert!(Malloced::bytes_eq(&a, &b));
+            assert_eq!(Malloced::bytes_eq(&a, &b), a == b);
+
+            b[(1 << 16) - 1] ^= 1;
+            assert!(!Malloced::bytes_eq(&a, &b));
+            assert_eq!(Malloced::bytes_eq(&a, &b), a == b);
+
+            assert!(!Malloced::bytes_eq(&a, &b[..100]));
+        }
+
+        #[test]
+        fn empty() {
+            let empty = Malloced::<[u8]>::alloc(&[]).unwrap();
+            assert!(Malloced::bytes_eq(&empty, &[]));
+            assert!(!Malloced::bytes_eq(&empty, &[0]));
+        }
+    }
+
+    mod swap {
+        use super::*;
+
+        #[test]
+        fn sized() {
+            let mut a = Malloced::alloc_one([1u64; 64]).unwrap();
+            let mut b = Malloced::alloc_one([2u64; 64]).unwrap();
+            let (a_ptr, b_ptr) = (Malloced::as_ptr(&a), Malloced::as_ptr(&b));
+
+            Malloced::swap(&mut a, &mut b);
+            assert_eq!(Malloced::as_ptr(&a), b_ptr);
+            assert_eq!(Malloced::as_ptr(&b), a_ptr);
+            assert_eq!(*a, [2; 64]);
+            assert_eq!(*b, [1; 64]);
+        }
+
+        #[test]
+        fn slice() {
+            let mut a = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            let mut b = Malloced::<[u8]>::alloc(&[4]).unwrap();
+            let (a_ptr, b_ptr) = (Malloced::as_ptr(&a), Malloced::as_ptr(&b));
+
+            Malloced::swap(&mut a, &mut b);
+            assert_eq!(Malloced::as_ptr(&a), b_ptr);
+            assert_eq!(Malloced::as_ptr(&b), a_ptr);
+            assert_eq!(&*a, &[4]);
+            assert_eq!(&*b, &[1, 2, 3]);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod cow {
+        use super::*;
+        use std::{borrow::Cow, string::String};
+
+        #[test]
+        fn str() {
+            let s = Malloced::alloc_str("hello").unwrap();
+            let cow: Cow<'static, str> = Malloced::into_cow(s);
+            assert!(matches!(cow, Cow::Owned(ref s) if s == "hello"));
+
+            fn takes_cow(s: impl Into<Cow<'static, str>>) -> String {
+                s.into().into_owned()
+            }
+
+            assert_eq!(takes_cow(Malloced::alloc_str("world").unwrap()), "world");
+        }
+
+        #[test]
+        fn slice() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            let cow: Cow<[u8]> = buf.into();
+            assert_eq!(cow.into_owned(), [1, 2, 3]);
+        }
+    }
+
+    mod replace {
+        use super::*;
+
+        #[test]
+        fn replace() {
+            let mut value = Malloced::alloc_one(1u32).unwrap();
+            let ptr = Malloced::as_ptr(&value);
+
+            assert_eq!(Malloced::replace(&mut value, 2), 1);
+            assert_eq!(*value, 2);
+            assert_eq!(Malloced::as_ptr(&value), ptr);
+        }
+
+        #[test]
+        fn take() {
+            let mut value = Malloced::alloc_one([1u8, 2, 3]).unwrap();
+            let ptr = Malloced::as_ptr(&value);
+
+            assert_eq!(Malloced::take(&mut value), [1, 2, 3]);
+            assert_eq!(*value, [0; 3]);
+            assert_eq!(Malloced::as_ptr(&value), ptr);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod try_map {
+        use super::*;
+        use crate::sys::malloc_count;
+        use core::{cell::Cell, convert::TryFrom};
+
+        #[test]
+        fn in_place() {
+            let value = Malloced::alloc_one(7i32).unwrap();
+            let ptr = Malloced::as_ptr(&value) as usize;
+
+            let count = malloc_count();
+            let value = Malloced::try_map(value, u32::try_from).unwrap();
+            assert_eq!(malloc_count(), count);
+            assert_eq!(Malloced::as_ptr(&value) as usize, ptr);
+            assert_eq!(*value, 7);
+
+            // Smaller types also reuse the allocation.
+            let value = Malloced::alloc_one("42").unwrap();
+            let ptr = Malloced::as_ptr(&value) as usize;
+
+            let count = malloc_count();
+            let value: Malloced<u32> = Malloced::try_map(value, str::parse).unwrap();
+            assert_eq!(malloc_count(), count);
+            assert_eq!(Malloced::as_ptr(&value) as usize, ptr);
+            assert_eq!(*value, 42);
+        }
+
+        #[test]
+        fn realloc() {
+            let value = Malloced::alloc_one(3u8).unwrap();
+
+            let count = malloc_count();
+            let value = Malloced::try_map(value, |n| Ok::<_, ()>([n as u64; 4])).unwrap();
+            assert_eq!(malloc_count(), count + 1);
+            assert_eq!(*value, [3; 4]);
+        }
+
+        #[test]
+        fn err() {
+            let drops = Cell::new(0);
+            let value = Malloced::alloc_one(DropCounter(&drops)).unwrap();
+
+            let result = Malloced::try_map(value, |counter| -> Result<u8, _> {
+                drop(counter);
+                Err("failed")
+            });
+            assert_eq!(result.unwrap_err(), "failed");
+            assert_eq!(drops.get(), 1);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod clone_into {
+        use super::*;
+        use crate::sys::malloc_count;
+        use std::string::String;
+
+        #[test]
+        fn sized() {
+            let mut target = Malloced::alloc_one(String::from("old")).unwrap();
+            let ptr = Malloced::as_ptr(&target);
+
+            let count = malloc_count();
+            Malloced::clone_into(&String::from("new"), &mut target);
+
+            assert_eq!(malloc_count(), count);
+            assert_eq!(Malloced::as_ptr(&target), ptr);
+            assert_eq!(*target, "new");
+        }
+
+        #[test]
+        fn slice_same_len() {
+            let mut target = Malloced::<[u32]>::alloc(&[1, 2, 3]).unwrap();
+            let ptr = Malloced::as_ptr(&target);
+
+            let count = malloc_count();
+            Malloced::clone_slice_into(&[4, 5, 6], &mut target);
+
+            assert_eq!(malloc_count(), count);
+            assert_eq!(Malloced::as_ptr(&target), ptr);
+            assert_eq!(&*target, &[4, 5, 6]);
+        }
+
+        #[test]
+        fn slice_different_len() {
+            let mut target = Malloced::<[String]>::alloc(&[String::from("a")]).unwrap();
+
+            let count = malloc_count();
+            Malloced::clone_slice_into(&[String::from("b"), String::from("c")], &mut target);
+
+            assert_eq!(malloc_count(), count + 1);
+            assert_eq!(&*target, &["b", "c"]);
+        }
+    }
+
+    mod eq {
+        use super::*;
+
+        #[test]
+        fn ref_value() {
+            let value = Malloced::alloc_one(5i32).unwrap();
+            assert!(value == &5);
+            assert!(value != &6);
+
+            let buf = Malloced::<[u8]>::alloc(&[1, 2]).unwrap();
+            let (equal, shorter): (&[u8], &[u8]) = (&[1, 2], &[1]);
+            assert!(buf == equal);
+            assert!(buf != shorter);
+
+            assert!(buf == Malloced::<[u8]>::alloc(&[1, 2]).unwrap());
+        }
+
+        #[test]
+        fn cross_type_len() {
+            // Panics if any element comparison runs.
+            #[derive(Clone)]
+            struct Never;
+
+            impl PartialEq<u8> for Never {
+                fn eq(&self, _: &u8) -> bool {
+                    panic!("compared elements of slices with different lengths");
+                }
+            }
+
+            let nevers = Malloced::<[Never]>::alloc(&[Never, Never]).unwrap();
+            let bytes = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+
+            assert!(nevers != [1u8, 2, 3][..]);
+            assert!(nevers != [][..]);
+            assert!([Never][..] != bytes);
+        }
+    }
+
+    mod as_ref {
+        use super::*;
+
+        fn bytes<B: AsRef<[u8]> + ?Sized>(b: &B) -> &[u8] {
+            b.as_ref()
+        }
+
+        #[test]
+        fn str() {
+            let s = Malloced::alloc_str("héllo").unwrap();
+            assert_eq!(bytes(&s), "héllo".as_bytes());
+
+            let empty = Malloced::alloc_str("").unwrap();
+            assert_eq!(bytes(&empty), b"");
+        }
+
+        #[test]
+        fn c_str() {
+            let s = Malloced::<CStr>::from_bytes_with_nul(b"hello\0").unwrap();
+            assert_eq!(bytes(&s), b"hello");
+
+            let empty = Malloced::<CStr>::from_bytes_with_nul(b"\0").unwrap();
+            assert_eq!(bytes(&empty), b"");
+        }
+    }
 
-impl Malloced<dyn Any + Send> {
-    /// Attempt to downcast the instance to a concrete type.
-    #[inline]
-    pub fn downcast<T: Any>(self) -> Result<Malloced<T>, Self> {
-        if self.is::<T>() {
-            let raw: *mut (dyn Any + Send) = Malloced::into_raw(self);
-            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
-        } else {
-            Err(self)
+    #[cfg(feature = "std")]
+    mod borrow {
+        use super::*;
+        use std::collections::{HashMap, HashSet};
+
+        #[test]
+        fn str_keys() {
+            let mut map = HashMap::new();
+            map.insert(Malloced::alloc_str("one").unwrap(), 1);
+            map.insert(Malloced::alloc_str("two").unwrap(), 2);
+
+            assert_eq!(map.get("one"), Some(&1));
+            assert_eq!(map.get("two"), Some(&2));
+            assert_eq!(map.get("three"), None);
+            assert_eq!(map.remove("one"), Some(1));
+            assert!(!map.contains_key("one"));
+        }
+
+        #[test]
+        fn slice_keys() {
+            let mut set = HashSet::new();
+            set.insert(Malloced::<[u8]>::alloc(&[1, 2]).unwrap());
+            set.insert(Malloced::<[u8]>::alloc(&[3]).unwrap());
+
+            assert!(set.contains(&[1, 2][..]));
+            assert!(set.contains(&[3][..]));
+            assert!(!set.contains(&[1][..]));
         }
     }
-}
 
-impl Malloced<dyn Any + Send + Sync> {
-    /// Attempt to downcast the instance to a concrete type.
-    #[inline]
-    pub fn downcast<T: Any>(self) -> Result<Malloced<T>, Self> {
-        if self.is::<T>() {
-            let raw: *mut (dyn Any + Send + Sync) = Malloced::into_raw(self);
-            Ok(unsafe { Malloced::from_raw(raw as *mut T) })
-        } else {
-            Err(self)
+    mod ord {
+        use super::*;
+        use core::cmp::Ordering;
+
+        #[test]
+        fn slice() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+
+            assert!(buf < [1, 2, 4][..]);
+            assert!(buf > [1, 2][..]);
+            assert!(buf <= [1, 2, 3][..]);
+            assert!(buf == [1, 2, 3][..]);
+            assert_eq!(buf.partial_cmp(&[1, 2, 3][..]), Some(Ordering::Equal));
+
+            assert!([1, 2, 4][..] > buf);
+            assert!([0][..] < buf);
+            assert!([1, 2, 3][..] == buf);
+        }
+
+        #[test]
+        fn str() {
+            let s = Malloced::alloc_str("abc").unwrap();
+
+            assert!(s < *"abd");
+            assert!(s > *"ab");
+            assert!(s == *"abc");
+            assert!(s < *"b");
+
+            assert!(*"abd" > s);
+            assert!(*"abc" == s);
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    mod non_null {
+        use super::*;
+
+        #[test]
+        fn sized() {
+            let value = Malloced::alloc_one(42u32).unwrap();
+            let ptr = Malloced::as_non_null(&value);
+            assert_eq!(ptr.as_ptr() as *const u32, Malloced::as_ptr(&value));
+
+            let ptr: NonNull<u32> = value.into();
+            let result = unsafe { Malloced::from_non_null(ptr) };
+            assert_eq!(*result, 42);
+        }
+
+        #[test]
+        fn slice() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            let ptr = Malloced::as_non_null(&buf);
+            assert_eq!(ptr.as_ptr() as *const [u8], Malloced::as_ptr(&buf));
+
+            let ptr: NonNull<[u8]> = buf.into();
+            let result = unsafe { Malloced::from_non_null(ptr) };
+            assert_eq!(&*result, &[1, 2, 3]);
+        }
+    }
+
+    #[cfg(feature = "bytemuck")]
+    mod bytemuck {
+        use super::*;
+
+        #[test]
+        fn cast_slice() {
+            let mut bytes = [0u8; 8];
+            bytes[..4].copy_from_slice(&1u32.to_ne_bytes());
+            bytes[4..].copy_from_slice(&2u32.to_ne_bytes());
+
+            // `malloc` returns memory suitably aligned for `u32`.
+            let buf = Malloced::<[u8]>::alloc(&bytes).unwrap();
+
+            let result = Malloced::cast_slice::<u32>(buf);
+            assert_eq!(&*result, &[1, 2]);
+        }
+
+        #[test]
+        #[should_panic(expected = "cast_slice>OutputSliceWouldHaveSlop")]
+        fn cast_slice_size_mismatch() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            Malloced::cast_slice::<u16>(buf);
+        }
+    }
+
+    mod size {
+        use super::*;
+
+        #[test]
+        fn good_size() {
+            for n in [0, 1, 7, 8, 24, 100, 1000, 4096, 100_000] {
+                assert!(crate::good_size(n) >= n);
+            }
+        }
+
+        #[test]
+        fn allocation_size() {
+            let value = Malloced::alloc_one(1u64).unwrap();
+            assert_eq!(Malloced::allocation_size(&value), 8);
+
+            let buf = Malloced::<[u16]>::alloc(&[1, 2, 3]).unwrap();
+            assert_eq!(Malloced::allocation_size(&buf), 6);
+
+            let s = Malloced::alloc_str("héllo").unwrap();
+            assert_eq!(Malloced::allocation_size(&s), 6);
+
+            let zst = Malloced::<[()]>::alloc(&[(); 4]).unwrap();
+            assert_eq!(Malloced::allocation_size(&zst), 0);
+        }
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "ios",
+            windows,
+        ))]
+        #[test]
+        fn usable_size() {
+            let value = Malloced::alloc_one(1u64).unwrap();
+            assert!(Malloced::usable_size(&value) >= mem::size_of::<u64>());
+
+            let buf = Malloced::<[u8]>::alloc(&[0; 100]).unwrap();
+            assert!(Malloced::usable_size(&buf) >= 100);
+        }
+
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        #[test]
+        fn clone_with_capacity() {
+            // glibc hands out a free chunk whole if splitting it would leave
+            // too little, so other test threads can cause a larger usable
+            // size. Holding on to such chunks until an exact one is found, and
+            // then freeing it, makes the clone reuse it from this thread's
+            // cache.
+            fn check<T: Copy + PartialEq + core::fmt::Debug>(len: usize, f: fn(usize) -> T) {
+                let buf = Malloced::<[T]>::from_fn(len, f);
+
+                let mut oversized = alloc::vec::Vec::new();
+                loop {
+                    let spare = Malloced::clone_with_capacity(&buf);
+                    if Malloced::usable_size(&spare) == Malloced::usable_size(&buf) {
+                        break;
+                    }
+                    oversized.push(spare);
+                }
+
+                let clone = Malloced::clone_with_capacity(&buf);
+                assert_eq!(clone, buf);
+                assert_ne!(Malloced::as_ptr(&clone), Malloced::as_ptr(&buf));
+                assert_eq!(Malloced::usable_size(&clone), Malloced::usable_size(&buf));
+            }
+
+            for &len in &[1, 13, 100, 1000] {
+                check(len, |i| i as u8);
+            }
+            check(10, |i| i as u32);
+        }
+
+        #[test]
+        fn clone() {
+            let buf = Malloced::<[u8]>::from_fn(100, |i| i as u8);
+            let clone = buf.clone();
+            assert_eq!(clone, buf);
+            assert_eq!(Malloced::allocation_size(&clone), 100);
+
+            let empty = Malloced::<[u8]>::alloc(&[]).unwrap();
+            assert!(empty.clone().is_empty());
+            assert!(Malloced::clone_with_capacity(&empty).is_empty());
+
+            let zst = Malloced::<[()]>::alloc(&[(); 3]).unwrap();
+            assert_eq!(zst.clone().len(), 3);
+            assert_eq!(Malloced::clone_with_capacity(&zst).len(), 3);
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn clone_non_copy() {
+            use std::string::String;
+
+            let buf = Malloced::<[String]>::from_fn(2, |i| i.to_string());
+            assert_eq!(buf.clone(), buf);
+        }
+    }
+
+    mod slice {
+        use super::*;
+
+        #[test]
+        fn copy_from_slice() {
+            let mut buf = Malloced::<[u32]>::new_uninit_slice(3);
+            Malloced::copy_from_slice(&mut buf, &[1, 2, 3]);
+
+            let buf = unsafe { Malloced::assume_init(buf) };
+            assert_eq!(&*buf, &[1, 2, 3]);
+
+            let mut empty = Malloced::<[u32]>::new_uninit_slice(0);
+            Malloced::copy_from_slice(&mut empty, &[]);
+            assert_eq!(unsafe { &*Malloced::assume_init(empty) }, &[]);
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn from_reader() {
+            let mut reader = std::io::Cursor::new(b"hello world");
+
+            let buf = Malloced::<[u8]>::from_reader(&mut reader, 5).unwrap();
+            assert_eq!(&*buf, b"hello");
+
+            let buf = Malloced::<[u8]>::from_reader(&mut reader, 0).unwrap();
+            assert_eq!(&*buf, b"");
+
+            let error = Malloced::<[u8]>::from_reader(&mut reader, 7).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+        }
+
+        #[test]
+        #[should_panic = "source slice length does not match"]
+        fn copy_from_slice_len_mismatch() {
+            let mut buf = Malloced::<[u32]>::new_uninit_slice(3);
+            Malloced::copy_from_slice(&mut buf, &[1, 2]);
+        }
+
+        #[test]
+        fn from_array() {
+            let array = Malloced::alloc_one([1u16, 2, 3]).unwrap();
+            let ptr = Malloced::as_ptr(&array) as *const u16;
+
+            let buf: Malloced<[u16]> = array.into();
+            assert_eq!(&*buf, &[1, 2, 3]);
+            assert_eq!(Malloced::as_ptr(&buf) as *const u16, ptr);
+
+            let buf = Malloced::into_slice(Malloced::alloc_one([(); 4]).unwrap());
+            assert_eq!(buf.len(), 4);
+
+            let buf = Malloced::into_slice(Malloced::alloc_one([0u8; 0]).unwrap());
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn from_fn() {
+            let buf = Malloced::<[usize]>::from_fn(4, |i| i * i);
+            assert_eq!(&*buf, &[0, 1, 4, 9]);
+
+            let buf = Malloced::<[u8]>::from_fn(0, |_| unreachable!());
+            assert!(buf.is_empty());
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn from_fn_panic() {
+            use core::cell::Cell;
+            use std::panic::{self, AssertUnwindSafe};
+
+            let drops = Cell::new(0);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                Malloced::<[DropCounter]>::from_fn(5, |i| {
+                    assert!(i < 3, "fail");
+                    DropCounter(&drops)
+                })
+            }));
+
+            assert!(result.is_err());
+            assert_eq!(drops.get(), 3);
+        }
+
+        #[test]
+        fn new_slice_in_place() {
+            // Stands in for a C function shaped like `void fill(u8 *buf, size_t n)`.
+            unsafe fn memset(buf: *mut u8, n: usize) {
+                core::ptr::write_bytes(buf, 0xab, n);
+            }
+
+            let buf =
+                unsafe { Malloced::<[u8]>::new_slice_in_place(16, |ptr, len| memset(ptr, len)) };
+            assert_eq!(&*buf, &[0xab; 16]);
+
+            let buf =
+                unsafe { Malloced::<[u8]>::new_slice_in_place(0, |_, len| assert_eq!(len, 0)) };
+            assert!(buf.is_empty());
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn new_slice_in_place_panic() {
+            use std::panic::{self, AssertUnwindSafe};
+
+            struct PanicOnDrop;
+
+            impl Drop for PanicOnDrop {
+                fn drop(&mut self) {
+                    panic!("dropped uninitialized element");
+                }
+            }
+
+            let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+                Malloced::<[PanicOnDrop]>::new_slice_in_place(4, |_, _| panic!("fail"))
+            }));
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn repeat() {
+            let buf = Malloced::<[u8]>::repeat(&7, 1000);
+            assert_eq!(buf.len(), 1000);
+            assert!(buf.iter().all(|&b| b == 7));
+
+            let buf = Malloced::<[[u32; 3]]>::repeat(&[1, 2, 3], 5);
+            assert_eq!(&*buf, &[[1, 2, 3]; 5]);
+
+            assert!(Malloced::<[u8]>::repeat(&7, 0).is_empty());
+            assert_eq!(&*Malloced::<[i8]>::repeat(&-1, 2), &[-1, -1]);
+            assert_eq!(&*Malloced::<[bool]>::repeat(&true, 2), &[true, true]);
+            assert_eq!(Malloced::<[()]>::repeat(&(), 3).len(), 3);
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn repeat_clone() {
+            use std::string::String;
+
+            let buf = Malloced::<[String]>::repeat(&String::from("ab"), 3);
+            assert_eq!(&*buf, &["ab", "ab", "ab"]);
+        }
+
+        #[test]
+        fn into_raw_parts() {
+            let buf = Malloced::<[u16]>::alloc(&[1, 2, 3]).unwrap();
+
+            let (ptr, len) = Malloced::into_raw_parts(buf);
+            assert_eq!(len, 3);
+
+            let result = unsafe { Malloced::slice_from_raw_parts(ptr, len) };
+            assert_eq!(&*result, &[1, 2, 3]);
+        }
+    }
 
     mod c_str {
         use super::*;
@@ -400,5 +2575,218 @@ mod tests {
             let result = unsafe { Malloced::<CStr>::from_ptr(ptr) };
             assert_eq!(result.to_bytes(), b"hi");
         }
+
+        #[test]
+        fn bytes() {
+            let hi = Malloced::<CStr>::from_bytes_with_nul(b"hi\0").unwrap();
+            assert_eq!(
+                Malloced::bytes(&hi).collect::<alloc::vec::Vec<u8>>(),
+                [b'h', b'i']
+            );
+
+            let mut iter = Malloced::bytes(&hi);
+            assert_eq!(iter.by_ref().count(), 2);
+            assert_eq!(iter.next(), None);
+
+            let empty = Malloced::<CStr>::from_bytes_with_nul(b"\0").unwrap();
+            assert_eq!(Malloced::bytes(&empty).next(), None);
+        }
+
+        #[test]
+        #[allow(clippy::op_ref)]
+        fn eq() {
+            let buf = Malloced::<[c_char]>::alloc(&[b'h' as _, b'i' as _, 0]).unwrap();
+            let ptr = ManuallyDrop::new(buf).ptr.as_ptr() as *mut c_char;
+            let result = unsafe { Malloced::<CStr>::from_ptr(ptr) };
+
+            let hi = CStr::from_bytes_with_nul(b"hi\0").unwrap();
+            let ho = CStr::from_bytes_with_nul(b"ho\0").unwrap();
+            let h = CStr::from_bytes_with_nul(b"h\0").unwrap();
+
+            assert!(result == *hi);
+            assert!(*hi == result);
+            assert!(result == hi);
+            assert!(hi == result);
+
+            assert!(result != *ho);
+            assert!(*ho != result);
+            assert!(result != h);
+            assert!(h != result);
+        }
+
+        #[test]
+        fn try_from_ptr() {
+            let result = unsafe { Malloced::<CStr>::try_from_ptr(core::ptr::null_mut()) };
+            assert!(result.is_none());
+
+            let buf = Malloced::<[c_char]>::alloc(&[b'h' as _, b'i' as _, 0]).unwrap();
+            let ptr = ManuallyDrop::new(buf).ptr.as_ptr() as *mut c_char;
+
+            let result = unsafe { Malloced::<CStr>::try_from_ptr(ptr) }.unwrap();
+            assert_eq!(result.to_bytes(), b"hi");
+        }
+
+        #[test]
+        fn from_bytes_with_nul() {
+            let result = Malloced::<CStr>::from_bytes_with_nul(b"hi\0").unwrap();
+            assert_eq!(result.to_bytes(), b"hi");
+
+            let result = Malloced::<CStr>::from_bytes_with_nul(b"\0").unwrap();
+            assert_eq!(result.to_bytes(), b"");
+        }
+
+        #[test]
+        fn count_bytes() {
+            let empty = Malloced::<CStr>::from_bytes_with_nul(b"\0").unwrap();
+            assert_eq!(Malloced::count_bytes(&empty), 0);
+
+            let hello = Malloced::<CStr>::from_bytes_with_nul(b"hello\0").unwrap();
+            assert_eq!(Malloced::count_bytes(&hello), 5);
+        }
+
+        #[test]
+        fn from_bytes_with_nul_missing_nul() {
+            assert!(Malloced::<CStr>::from_bytes_with_nul(b"hi").is_err());
+            assert!(Malloced::<CStr>::from_bytes_with_nul(b"").is_err());
+        }
+
+        #[test]
+        fn from_bytes_with_nul_interior_nul() {
+            assert!(Malloced::<CStr>::from_bytes_with_nul(b"h\0i\0").is_err());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod fmt_write {
+        use super::*;
+        use core::fmt::Write;
+        use std::string::String;
+
+        #[test]
+        fn string() {
+            let mut s = Malloced::alloc_one(String::new()).unwrap();
+            write!(s, "{}+{}", 1, 2).unwrap();
+            s.write_char('=').unwrap();
+            s.write_str("3").unwrap();
+            assert_eq!(*s, "1+2=3");
+        }
+    }
+
+    mod hash {
+        use super::*;
+        use core::hash::{Hash, Hasher};
+
+        /// A deterministic FNV-1a hasher, so that hashes can be compared
+        /// across values.
+        struct Fnv(u64);
+
+        impl Hasher for Fnv {
+            fn finish(&self) -> u64 {
+                self.0
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                for &byte in bytes {
+                    self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100_0000_01b3);
+                }
+            }
+        }
+
+        fn hash<T: ?Sized + Hash>(value: &T) -> u64 {
+            let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        #[test]
+        fn str() {
+            for s in ["", "a", "hello", "héllo"] {
+                let m = Malloced::alloc_str(s).unwrap();
+                assert_eq!(hash(&m), hash(s));
+                assert_eq!(hash(&m), hash(&s));
+            }
+
+            // Length-prefixing keeps adjacent strings from colliding.
+            let ab = (
+                Malloced::alloc_str("ab").unwrap(),
+                Malloced::alloc_str("c").unwrap(),
+            );
+            let bc = (
+                Malloced::alloc_str("a").unwrap(),
+                Malloced::alloc_str("bc").unwrap(),
+            );
+            assert_eq!(hash(&ab), hash(&("ab", "c")));
+            assert_ne!(hash(&ab), hash(&bc));
+        }
+
+        #[test]
+        fn slice() {
+            let bytes = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            assert_eq!(hash(&bytes), hash(&[1u8, 2, 3][..]));
+
+            let words = Malloced::<[u32]>::alloc(&[1, 2, 3]).unwrap();
+            assert_eq!(hash(&words), hash(&[1u32, 2, 3][..]));
+        }
+
+        #[test]
+        fn sized() {
+            let value = Malloced::alloc_one(42u64).unwrap();
+            assert_eq!(hash(&value), hash(&42u64));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod build_hasher {
+        use super::*;
+        use std::collections::{hash_map::RandomState, HashMap};
+
+        #[test]
+        fn hash_map() {
+            let hasher = Malloced::alloc_one(RandomState::new()).unwrap();
+            let mut map = HashMap::with_hasher(hasher);
+            map.insert("a", 1);
+            map.insert("b", 2);
+
+            assert_eq!(map.get("a"), Some(&1));
+            assert_eq!(map.get("b"), Some(&2));
+            assert_eq!(map.get("c"), None);
+        }
+    }
+
+    mod index {
+        use super::*;
+
+        #[test]
+        fn slice() {
+            let mut buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4]).unwrap();
+            assert_eq!(buf[0], 1);
+            assert_eq!(&buf[1..3], &[2, 3]);
+            assert_eq!(&buf[..], &[1, 2, 3, 4]);
+
+            buf[3] = 5;
+            buf[..2].copy_from_slice(&[6, 7]);
+            assert_eq!(&*buf, &[6, 7, 3, 5]);
+        }
+
+        #[test]
+        #[should_panic]
+        fn slice_out_of_bounds() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2]).unwrap();
+            let _ = buf[2];
+        }
+
+        #[test]
+        #[cfg(feature = "std")]
+        fn hash_map() {
+            use std::{collections::HashMap, string::String};
+
+            let mut map = HashMap::new();
+            map.insert(String::from("a"), 1);
+            map.insert(String::from("b"), 2);
+
+            let map = Malloced::alloc_one(map).unwrap();
+            assert_eq!(map["a"], 1);
+            assert_eq!(map["b"], 2);
+        }
     }
 }
@@ -0,0 +1,110 @@
+//! Specializations that require a nightly compiler.
+
+use crate::{capacity_overflow, Malloced, MallocedVec};
+use core::{iter::TrustedLen, ptr};
+
+/// Specializes [`Extend`] for [`TrustedLen`] iterators.
+pub(crate) trait SpecExtend<T, I> {
+    fn spec_extend(&mut self, iter: I);
+}
+
+impl<T, I> SpecExtend<T, I> for MallocedVec<T>
+where
+    I: Iterator<Item = T>,
+{
+    #[inline]
+    default fn spec_extend(&mut self, iter: I) {
+        self.extend_desugared(iter);
+    }
+}
+
+impl<T, I> SpecExtend<T, I> for MallocedVec<T>
+where
+    I: TrustedLen<Item = T>,
+{
+    #[inline]
+    fn spec_extend(&mut self, iter: I) {
+        // A `TrustedLen` iterator's upper bound is exact, and is only `None`
+        // if the length exceeds `usize::MAX`.
+        let additional = match iter.size_hint() {
+            (_, Some(upper)) => upper,
+            (_, None) => capacity_overflow(),
+        };
+
+        // SAFETY: `TrustedLen` guarantees that `additional` is exact, so the
+        // allocation is made once up front.
+        unsafe { self.extend_trusted(iter, additional) };
+    }
+}
+
+/// Specializes [`Malloced::repeat`] for byte types.
+pub(crate) trait SpecRepeat: Sized {
+    fn spec_repeat(value: &Self, len: usize) -> Malloced<[Self]>;
+}
+
+impl<T: Clone> SpecRepeat for T {
+    #[inline]
+    default fn spec_repeat(value: &T, len: usize) -> Malloced<[T]> {
+        Malloced::from_fn(len, |_| value.clone())
+    }
+}
+
+macro_rules! spec_repeat_bytes {
+    ($($t:ty),+) => {$(
+        impl SpecRepeat for $t {
+            #[inline]
+            fn spec_repeat(value: &$t, len: usize) -> Malloced<[$t]> {
+                let mut buf = Malloced::<[$t]>::new_uninit_slice(len);
+                unsafe {
+                    ptr::write_bytes(Malloced::as_mut_ptr(&mut buf) as *mut u8, *value as u8, len);
+                    Malloced::assume_init(buf)
+                }
+            }
+        }
+    )+};
+}
+
+spec_repeat_bytes!(u8, i8, bool);
+
+// Counting allocations requires the `std` feature.
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{sys::malloc_count, Malloced};
+
+    /// Hides the `TrustedLen` implementation of the wrapped iterator while
+    /// keeping its exact size hint.
+    struct Untrusted<I>(I);
+
+    impl<I: Iterator> Iterator for Untrusted<I> {
+        type Item = I::Item;
+
+        fn next(&mut self) -> Option<I::Item> {
+            self.0.next()
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.0.size_hint()
+        }
+    }
+
+    #[test]
+    fn from_iter_allocation_count() {
+        let count = malloc_count();
+        let trusted: Malloced<[u32]> = (0..3).collect();
+        assert_eq!(malloc_count() - count, 1);
+
+        let count = malloc_count();
+        let untrusted: Malloced<[u32]> = Untrusted(0..3).collect();
+        assert!(malloc_count() - count > 1);
+
+        assert_eq!(trusted, untrusted);
+    }
+
+    #[test]
+    fn from_iter_map() {
+        let count = malloc_count();
+        let slice: Malloced<[u64]> = (0..100u64).map(|x| x * 2).collect();
+        assert_eq!(malloc_count() - count, 1);
+        assert_eq!(slice.iter().sum::<u64>(), (0..100).map(|x| x * 2).sum());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::Malloced;
+use core::ops::{
+    Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Div, DivAssign,
+    Mul, MulAssign, Neg, Not, Rem, RemAssign, Shl, ShlAssign, Shr, ShrAssign, Sub, SubAssign,
+};
+
+/// Forwards a unary operator to the inner value.
+macro_rules! forward_unary_op {
+    ($Op:ident, $op:ident) => {
+        /// Applies the operator to the inner value.
+        ///
+        /// The result is moved into a new `malloc`-ed allocation, and the
+        /// memory of the operand is freed.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `malloc` fails.
+        impl<T: $Op> $Op for Malloced<T> {
+            type Output = Malloced<T::Output>;
+
+            #[inline]
+            fn $op(self) -> Self::Output {
+                Malloced::from_value(Malloced::into_inner(self).$op())
+            }
+        }
+    };
+}
+
+/// Forwards a binary operator and its assigning form to the inner values.
+macro_rules! forward_binary_op {
+    ($Op:ident, $op:ident, $OpAssign:ident, $op_assign:ident) => {
+        /// Applies the operator to the inner values.
+        ///
+        /// The result is moved into a new `malloc`-ed allocation, and the
+        /// memory of both operands is freed.
+        ///
+        /// # Panics
+        ///
+        /// Panics if `malloc` fails.
+        impl<T: $Op<U>, U> $Op<Malloced<U>> for Malloced<T> {
+            type Output = Malloced<T::Output>;
+
+            #[inline]
+            fn $op(self, rhs: Malloced<U>) -> Self::Output {
+                let lhs = Malloced::into_inner(self);
+                let rhs = Malloced::into_inner(rhs);
+                Malloced::from_value(lhs.$op(rhs))
+            }
+        }
+
+        /// Applies the operator to the inner value in place.
+        ///
+        /// This does not allocate. The memory of `rhs` is freed.
+        impl<T: ?Sized + $OpAssign<U>, U> $OpAssign<Malloced<U>> for Malloced<T> {
+            #[inline]
+            fn $op_assign(&mut self, rhs: Malloced<U>) {
+                T::$op_assign(self, Malloced::into_inner(rhs));
+            }
+        }
+    };
+}
+
+forward_binary_op!(Add, add, AddAssign, add_assign);
+forward_binary_op!(Sub, sub, SubAssign, sub_assign);
+forward_binary_op!(Mul, mul, MulAssign, mul_assign);
+forward_binary_op!(Div, div, DivAssign, div_assign);
+forward_binary_op!(Rem, rem, RemAssign, rem_assign);
+
+forward_unary_op!(Neg, neg);
+forward_unary_op!(Not, not);
+
+forward_binary_op!(BitAnd, bitand, BitAndAssign, bitand_assign);
+forward_binary_op!(BitOr, bitor, BitOrAssign, bitor_assign);
+forward_binary_op!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+forward_binary_op!(Shl, shl, ShlAssign, shl_assign);
+forward_binary_op!(Shr, shr, ShrAssign, shr_assign);
+
+#[cfg(test)]
+mod tests {
+    use crate::Malloced;
+
+    fn new<T>(value: T) -> Malloced<T> {
+        Malloced::alloc_one(value).unwrap()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(*(new(7u64) + new(5u64)), 12);
+        assert_eq!(*(new(7u64) - new(5u64)), 2);
+        assert_eq!(*(new(7u64) * new(5u64)), 35);
+        assert_eq!(*(new(7u64) / new(5u64)), 1);
+        assert_eq!(*(new(7u64) % new(5u64)), 2);
+    }
+
+    #[test]
+    fn assign() {
+        let mut value = new(7u64);
+        let ptr = Malloced::as_ptr(&value);
+
+        value += new(5);
+        assert_eq!(*value, 12);
+        value -= new(2);
+        assert_eq!(*value, 10);
+        value *= new(3);
+        assert_eq!(*value, 30);
+        value /= new(4);
+        assert_eq!(*value, 7);
+        value %= new(4);
+        assert_eq!(*value, 3);
+
+        assert_eq!(Malloced::as_ptr(&value), ptr);
+    }
+
+    #[test]
+    fn unary() {
+        assert_eq!(*-new(5i32), -5);
+        assert_eq!(*!new(0b1010_0101u8), 0b0101_1010);
+        assert!(!*!new(true));
+    }
+
+    #[test]
+    fn bitwise() {
+        let flags = 0b1100u8;
+        let other = 0b1010u8;
+
+        assert_eq!(*(new(flags) & new(other)), 0b1000);
+        assert_eq!(*(new(flags) | new(other)), 0b1110);
+        assert_eq!(*(new(flags) ^ new(other)), 0b0110);
+        assert_eq!(*(new(flags) << new(2u32)), 0b11_0000);
+        assert_eq!(*(new(flags) >> new(2u32)), 0b11);
+    }
+
+    #[test]
+    fn bitwise_assign() {
+        let mut flags = new(0b1100u8);
+        let ptr = Malloced::as_ptr(&flags);
+
+        flags &= new(0b0110);
+        assert_eq!(*flags, 0b0100);
+        flags |= new(0b0001);
+        assert_eq!(*flags, 0b0101);
+        flags ^= new(0b1111);
+        assert_eq!(*flags, 0b1010);
+        flags <<= new(1u32);
+        assert_eq!(*flags, 0b1_0100);
+        flags >>= new(2u32);
+        assert_eq!(*flags, 0b101);
+
+        assert_eq!(Malloced::as_ptr(&flags), ptr);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn non_copy() {
+        use std::string::String;
+
+        let mut s = new(String::from("a"));
+        s += new("b");
+        assert_eq!(*s, "ab");
+
+        let s = new(String::from("c")) + new("d");
+        assert_eq!(*s, "cd");
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{FreeGuard, Malloced};
+use core::{
+    ffi::c_void,
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+    slice,
+};
+use rayon::iter::{
+    plumbing::{bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer},
+    IndexedParallelIterator, IntoParallelIterator, ParallelIterator,
+};
+
+/// A parallel iterator over a
+/// <code>[Malloced](crate::Malloced)<[\[T\]](prim@slice)></code>.
+///
+/// This requires the `rayon` feature.
+pub struct SliceParIter<T> {
+    buf: NonNull<T>,
+    // Marks ownership of an instance of T.
+    marker: PhantomData<T>,
+    len: usize,
+}
+
+unsafe impl<T: Send> Send for SliceParIter<T> {}
+unsafe impl<T: Sync> Sync for SliceParIter<T> {}
+
+impl<T> Drop for SliceParIter<T> {
+    #[inline]
+    fn drop(&mut self) {
+        // Only reached if the iterator was never driven, in which case it
+        // still owns all of its elements.
+        drop(unsafe { Malloced::slice_from_raw_parts(self.buf.as_ptr(), self.len) });
+    }
+}
+
+impl<T: Send> IntoParallelIterator for Malloced<[T]> {
+    type Iter = SliceParIter<T>;
+    type Item = T;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        let (buf, len) = Malloced::into_raw_parts(self);
+        SliceParIter {
+            buf: unsafe { NonNull::new_unchecked(buf) },
+            marker: PhantomData,
+            len,
+        }
+    }
+}
+
+impl<'a, T: Sync> IntoParallelIterator for &'a Malloced<[T]> {
+    type Iter = rayon::slice::Iter<'a, T>;
+    type Item = &'a T;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        <&[T]>::into_par_iter(self)
+    }
+}
+
+impl<T: Send> ParallelIterator for SliceParIter<T> {
+    type Item = T;
+
+    #[inline]
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    #[inline]
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<T: Send> IndexedParallelIterator for SliceParIter<T> {
+    #[inline]
+    fn drive<C>(self, consumer: C) -> C::Result
+    where
+        C: Consumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn with_producer<CB>(self, callback: CB) -> CB::Output
+    where
+        CB: ProducerCallback<Self::Item>,
+    {
+        let this = mem::ManuallyDrop::new(self);
+
+        // Frees the buffer once the producers are done with it, even if
+        // consuming the elements panics. Ownership of the elements themselves
+        // is passed to the producers.
+        let _guard = FreeGuard(this.buf.as_ptr() as *mut c_void);
+
+        let slice = unsafe { slice::from_raw_parts_mut(this.buf.as_ptr(), this.len) };
+        callback.callback(DrainProducer { slice })
+    }
+}
+
+/// Owns the elements of a sub-slice, dropping any that are not yielded.
+struct DrainProducer<'a, T> {
+    slice: &'a mut [T],
+}
+
+impl<'a, T> Drop for DrainProducer<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        let slice = mem::take(&mut self.slice);
+        unsafe { ptr::drop_in_place(slice) };
+    }
+}
+
+impl<'a, T: Send> Producer for DrainProducer<'a, T> {
+    type Item = T;
+    type IntoIter = SliceDrain<'a, T>;
+
+    #[inline]
+    fn into_iter(mut self) -> Self::IntoIter {
+        let slice = mem::take(&mut self.slice);
+        SliceDrain {
+            iter: slice.iter_mut(),
+        }
+    }
+
+    #[inline]
+    fn split_at(mut self, index: usize) -> (Self, Self) {
+        let slice = mem::take(&mut self.slice);
+        let (left, right) = slice.split_at_mut(index);
+        (
+            DrainProducer { slice: left },
+            DrainProducer { slice: right },
+        )
+    }
+}
+
+/// Yields the elements of a sub-slice by value, dropping any that remain.
+struct SliceDrain<'a, T> {
+    iter: slice::IterMut<'a, T>,
+}
+
+impl<'a, T> Drop for SliceDrain<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        let iter = mem::replace(&mut self.iter, [].iter_mut());
+        unsafe { ptr::drop_in_place(iter.into_slice()) };
+    }
+}
+
+impl<'a, T> Iterator for SliceDrain<'a, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.iter.next().map(|ptr| unsafe { ptr::read(ptr) })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for SliceDrain<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back().map(|ptr| unsafe { ptr::read(ptr) })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for SliceDrain<'a, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Malloced;
+    use alloc::{string::String, vec::Vec};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+    #[test]
+    fn sum() {
+        let values: Vec<u64> = (0..10_000).collect();
+        let expected: u64 = values.iter().sum();
+
+        let buf = Malloced::<[u64]>::alloc(&values).unwrap();
+        assert_eq!((&buf).into_par_iter().sum::<u64>(), expected);
+        assert_eq!(buf.into_par_iter().sum::<u64>(), expected);
+    }
+
+    #[test]
+    fn collect() {
+        let values: Vec<String> = (0..1_000).map(|i| i.to_string()).collect();
+
+        let buf = Malloced::<[String]>::alloc(&values).unwrap();
+        let result: Vec<String> = buf.into_par_iter().collect();
+        assert_eq!(result, values);
+    }
+
+    #[test]
+    fn partial() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Clone)]
+        struct DropCounter;
+
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let buf =
+            Malloced::<[DropCounter]>::alloc(&[DropCounter, DropCounter, DropCounter, DropCounter])
+                .unwrap();
+        DROPS.store(0, Ordering::Relaxed);
+
+        let taken: Vec<DropCounter> = buf.into_par_iter().take(1).collect();
+        assert_eq!(DROPS.load(Ordering::Relaxed), 3);
+
+        drop(taken);
+        assert_eq!(DROPS.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn undriven() {
+        let buf = Malloced::<[String]>::alloc(&[String::from("a")]).unwrap();
+        drop(buf.into_par_iter());
+    }
+}
@@ -0,0 +1,516 @@
+use crate::{malloc_array, sys, AbortOnUnwind, Malloced};
+use core::{
+    cell::Cell, ffi::c_void, fmt, marker::PhantomData, mem::ManuallyDrop, ops::Deref, ptr::NonNull,
+};
+
+/// The reference counts of a [`MallocedRc`], stored in their own `malloc`-ed
+/// allocation.
+///
+/// All strong pointers collectively hold one weak reference, so the header
+/// outlives the value until the last [`MallocedWeak`] is dropped.
+struct RcHeader {
+    strong: Cell<usize>,
+    weak: Cell<usize>,
+}
+
+impl RcHeader {
+    /// Acquires a strong reference, aborting if the count overflows.
+    #[inline]
+    fn inc_strong(&self) {
+        match self.strong.get().checked_add(1) {
+            Some(strong) => self.strong.set(strong),
+            None => refcount_overflow(),
+        }
+    }
+
+    /// Acquires a weak reference, aborting if the count overflows.
+    #[inline]
+    fn inc_weak(&self) {
+        match self.weak.get().checked_add(1) {
+            Some(weak) => self.weak.set(weak),
+            None => refcount_overflow(),
+        }
+    }
+
+    /// Releases a weak reference, returning `true` if it was the last.
+    #[inline]
+    fn dec_weak(&self) -> bool {
+        let weak = self.weak.get() - 1;
+        self.weak.set(weak);
+        weak == 0
+    }
+}
+
+/// Aborts the process, since a wrapped count would later free a shared value.
+#[cold]
+#[inline(never)]
+fn refcount_overflow() -> ! {
+    let _abort = AbortOnUnwind;
+    panic!("reference count overflow");
+}
+
+/// A single-threaded reference-counted pointer to `malloc`-ed data.
+///
+/// This is like [`Rc`](https://doc.rust-lang.org/std/rc/struct.Rc.html), but
+/// the value stays in the allocation of the [`Malloced`] it was created from,
+/// so memory received from C can be shared without copying. The reference
+/// count lives in a small separate `malloc`-ed header.
+///
+/// When the last `MallocedRc` for a value is dropped, the value is dropped and
+/// its memory is `free`-d. The header is `free`-d once no
+/// [`MallocedWeak`] pointers remain either.
+///
+/// This requires the `rc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use malloced::{Malloced, MallocedRc};
+///
+/// let data: Malloced<[u8]> = b"shared".iter().copied().collect();
+/// let a = MallocedRc::new(data);
+/// let b = a.clone();
+///
+/// assert_eq!(MallocedRc::strong_count(&a), 2);
+/// assert_eq!(&*b, b"shared");
+/// ```
+pub struct MallocedRc<T: ?Sized> {
+    header: NonNull<RcHeader>,
+    data: NonNull<T>,
+
+    // Marks ownership of an instance of T.
+    _marker: PhantomData<T>,
+}
+
+impl<T: ?Sized> MallocedRc<T> {
+    /// Takes shared ownership of `value` without moving it out of its
+    /// allocation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails to allocate the reference count.
+    #[inline]
+    pub fn new(value: Malloced<T>) -> Self {
+        let header = malloc_array::<RcHeader>(1);
+        unsafe {
+            header.as_ptr().write(RcHeader {
+                strong: Cell::new(1),
+                weak: Cell::new(1),
+            });
+        }
+
+        Self {
+            header,
+            data: Malloced::as_non_null(&ManuallyDrop::new(value)),
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn header(&self) -> &RcHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    /// Returns the number of `MallocedRc` pointers to this allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `MallocedRc::strong_count(this)` instead of `this.strong_count()`.
+    #[inline]
+    pub fn strong_count(this: &Self) -> usize {
+        this.header().strong.get()
+    }
+
+    /// Returns the number of [`MallocedWeak`] pointers to this allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `MallocedRc::weak_count(this)` instead of `this.weak_count()`.
+    #[inline]
+    pub fn weak_count(this: &Self) -> usize {
+        this.header().weak.get() - 1
+    }
+
+    /// Creates a new [`MallocedWeak`] pointer to this allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `MallocedRc::downgrade(this)` instead of `this.downgrade()`.
+    #[inline]
+    pub fn downgrade(this: &Self) -> MallocedWeak<T> {
+        this.header().inc_weak();
+
+        MallocedWeak {
+            header: this.header,
+            data: this.data,
+        }
+    }
+
+    /// Returns `true` if there are no other `MallocedRc` or [`MallocedWeak`]
+    /// pointers to this allocation.
+    #[inline]
+    fn is_unique(this: &Self) -> bool {
+        Self::strong_count(this) == 1 && Self::weak_count(this) == 0
+    }
+
+    /// Returns `true` if both pointers share the same allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `MallocedRc::ptr_eq(this, other)` instead of
+    /// `this.ptr_eq(other)`.
+    #[inline]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.header == other.header
+    }
+
+    /// Returns an immutable raw pointer to the data.
+    #[inline]
+    pub fn as_ptr(this: &Self) -> *const T {
+        this.data.as_ptr()
+    }
+
+    /// Returns a mutable reference to the value if there are no other
+    /// `MallocedRc` or [`MallocedWeak`] pointers to it.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `MallocedRc::get_mut(this)` instead of `this.get_mut()`.
+    #[inline]
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if Self::is_unique(this) {
+            Some(unsafe { this.data.as_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the inner [`Malloced`] if this is the only `MallocedRc` pointer
+    /// to it.
+    ///
+    /// Otherwise, `this` is returned back as an error. Any [`MallocedWeak`]
+    /// pointers can no longer be upgraded afterward.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `MallocedRc::try_unwrap(this)` instead of `this.try_unwrap()`.
+    #[inline]
+    pub fn try_unwrap(this: Self) -> Result<Malloced<T>, Self> {
+        if Self::strong_count(&this) != 1 {
+            return Err(this);
+        }
+
+        let this = ManuallyDrop::new(this);
+        let header = this.header();
+        header.strong.set(0);
+
+        unsafe {
+            if header.dec_weak() {
+                sys::free(this.header.as_ptr() as *mut c_void);
+            }
+            Ok(Malloced::from_non_null(this.data))
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for MallocedRc<T> {
+    #[inline]
+    fn drop(&mut self) {
+        let strong = self.header().strong.get() - 1;
+        self.header().strong.set(strong);
+
+        if strong == 0 {
+            unsafe {
+                drop(Malloced::from_non_null(self.data));
+
+                if self.header().dec_weak() {
+                    sys::free(self.header.as_ptr() as *mut c_void);
+                }
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> Clone for MallocedRc<T> {
+    /// Makes another pointer to the same allocation by incrementing the
+    /// reference count.
+    #[inline]
+    fn clone(&self) -> Self {
+        self.header().inc_strong();
+
+        Self {
+            header: self.header,
+            data: self.data,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for MallocedRc<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { self.data.as_ref() }
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for MallocedRc<T> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized> From<Malloced<T>> for MallocedRc<T> {
+    #[inline]
+    fn from(value: Malloced<T>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for MallocedRc<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for MallocedRc<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
+
+impl<T: ?Sized> fmt::Pointer for MallocedRc<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.data.fmt(f)
+    }
+}
+
+/// A non-owning pointer to the value of a [`MallocedRc`].
+///
+/// This does not keep the value alive, so it can be used to break reference
+/// cycles. Use [`upgrade`](Self::upgrade) to access the value if it still
+/// exists.
+///
+/// This requires the `rc` feature.
+///
+/// # Examples
+///
+/// ```
+/// use malloced::{Malloced, MallocedRc};
+///
+/// let data: Malloced<[u8]> = b"shared".iter().copied().collect();
+/// let strong = MallocedRc::new(data);
+/// let weak = MallocedRc::downgrade(&strong);
+///
+/// assert!(weak.upgrade().is_some());
+///
+/// drop(strong);
+/// assert!(weak.upgrade().is_none());
+/// ```
+pub struct MallocedWeak<T: ?Sized> {
+    header: NonNull<RcHeader>,
+
+    // Dangles once the strong count reaches zero.
+    data: NonNull<T>,
+}
+
+impl<T: ?Sized> MallocedWeak<T> {
+    #[inline]
+    fn header(&self) -> &RcHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    /// Attempts to get a [`MallocedRc`] to the value, returning `None` if it
+    /// has already been dropped.
+    #[inline]
+    pub fn upgrade(&self) -> Option<MallocedRc<T>> {
+        let header = self.header();
+        if header.strong.get() == 0 {
+            return None;
+        }
+
+        header.inc_strong();
+        Some(MallocedRc {
+            header: self.header,
+            data: self.data,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the number of [`MallocedRc`] pointers to the value.
+    #[inline]
+    pub fn strong_count(&self) -> usize {
+        self.header().strong.get()
+    }
+
+    /// Returns the number of `MallocedWeak` pointers to the value, or 0 if
+    /// there are no remaining [`MallocedRc`] pointers.
+    #[inline]
+    pub fn weak_count(&self) -> usize {
+        match self.strong_count() {
+            0 => 0,
+            _ => self.header().weak.get() - 1,
+        }
+    }
+
+    /// Returns `true` if both pointers share the same allocation.
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.header == other.header
+    }
+}
+
+impl<T: ?Sized> Drop for MallocedWeak<T> {
+    #[inline]
+    fn drop(&mut self) {
+        if self.header().dec_weak() {
+            unsafe { sys::free(self.header.as_ptr() as *mut c_void) };
+        }
+    }
+}
+
+impl<T: ?Sized> Clone for MallocedWeak<T> {
+    /// Makes another weak pointer to the same allocation.
+    #[inline]
+    fn clone(&self) -> Self {
+        self.header().inc_weak();
+
+        Self {
+            header: self.header,
+            data: self.data,
+        }
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for MallocedWeak<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("(Weak)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DropCounter;
+
+    #[test]
+    fn clone_drop() {
+        let drops = Cell::new(0);
+        let a = MallocedRc::new(Malloced::alloc_one(DropCounter(&drops)).unwrap());
+        assert_eq!(MallocedRc::strong_count(&a), 1);
+
+        let b = a.clone();
+        let c = b.clone();
+        assert_eq!(MallocedRc::strong_count(&a), 3);
+        assert!(MallocedRc::ptr_eq(&a, &c));
+
+        drop(a);
+        drop(c);
+        assert_eq!(MallocedRc::strong_count(&b), 1);
+        assert_eq!(drops.get(), 0);
+
+        drop(b);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn shares_allocation() {
+        let buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+        let ptr = Malloced::as_ptr(&buf);
+
+        let a: MallocedRc<[u8]> = buf.into();
+        let b = a.clone();
+        assert_eq!(MallocedRc::as_ptr(&a), ptr);
+        assert_eq!(MallocedRc::as_ptr(&b), ptr);
+        assert_eq!(&*b, &[1, 2, 3]);
+
+        let other = MallocedRc::new(Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap());
+        assert!(!MallocedRc::ptr_eq(&a, &other));
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut a = MallocedRc::new(Malloced::alloc_one(1u32).unwrap());
+        *MallocedRc::get_mut(&mut a).unwrap() += 1;
+
+        let b = a.clone();
+        assert!(MallocedRc::get_mut(&mut a).is_none());
+
+        drop(b);
+        assert_eq!(*MallocedRc::get_mut(&mut a).unwrap(), 2);
+    }
+
+    #[test]
+    fn try_unwrap() {
+        let drops = Cell::new(0);
+        let a = MallocedRc::new(Malloced::alloc_one(DropCounter(&drops)).unwrap());
+        let b = a.clone();
+
+        let a = MallocedRc::try_unwrap(a).err().unwrap();
+        drop(b);
+
+        let value = MallocedRc::try_unwrap(a).ok().unwrap();
+        assert_eq!(drops.get(), 0);
+
+        drop(value);
+        assert_eq!(drops.get(), 1);
+    }
+
+    mod weak {
+        use super::*;
+
+        #[test]
+        fn upgrade() {
+            let drops = Cell::new(0);
+            let strong = MallocedRc::new(Malloced::alloc_one(DropCounter(&drops)).unwrap());
+            let weak = MallocedRc::downgrade(&strong);
+            assert_eq!(MallocedRc::weak_count(&strong), 1);
+            assert_eq!(weak.strong_count(), 1);
+
+            let upgraded = weak.upgrade().unwrap();
+            assert!(MallocedRc::ptr_eq(&strong, &upgraded));
+            assert_eq!(weak.strong_count(), 2);
+
+            drop(strong);
+            drop(upgraded);
+            assert_eq!(drops.get(), 1);
+            assert!(weak.upgrade().is_none());
+            assert_eq!(weak.strong_count(), 0);
+            assert_eq!(weak.weak_count(), 0);
+        }
+
+        #[test]
+        fn drop_order() {
+            let drops = Cell::new(0);
+            let strong = MallocedRc::new(Malloced::alloc_one(DropCounter(&drops)).unwrap());
+            let a = MallocedRc::downgrade(&strong);
+            let b = a.clone();
+            assert!(a.ptr_eq(&b));
+            assert_eq!(a.weak_count(), 2);
+
+            // Dropping every weak pointer first must not free the value.
+            drop(a);
+            drop(b);
+            assert_eq!(MallocedRc::weak_count(&strong), 0);
+            assert_eq!(drops.get(), 0);
+
+            let weak = MallocedRc::downgrade(&strong);
+            drop(strong);
+            assert_eq!(drops.get(), 1);
+
+            // The header outlives the value for the remaining weak pointer.
+            assert!(weak.upgrade().is_none());
+        }
+
+        #[test]
+        fn unique() {
+            let mut strong = MallocedRc::new(Malloced::alloc_one(1u32).unwrap());
+            let weak = MallocedRc::downgrade(&strong);
+            assert!(MallocedRc::get_mut(&mut strong).is_none());
+
+            let value = MallocedRc::try_unwrap(strong).ok().unwrap();
+            assert!(weak.upgrade().is_none());
+            assert_eq!(*value, 1);
+        }
+    }
+}
@@ -0,0 +1,2404 @@
+use crate::{
+    capacity_overflow, malloc_aligned, malloc_array, realloc_array, try_realloc_array, Malloced,
+};
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    cmp::Ordering,
+    fmt,
+    iter::FusedIterator,
+    mem::{self, MaybeUninit},
+    pin::Pin,
+    ptr::{self, NonNull},
+    slice::{self, Chunks, ChunksExact, SliceIndex, Windows},
+};
+
+impl<T> Malloced<[T]> {
+    /// Returns a shared slice of the elements.
+    ///
+    /// This is equivalent to `&*this`, but can be named when `T` has a method
+    /// that would shadow it through [`Deref`](core::ops::Deref).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_slice(this)` instead of `this.as_slice()`.
+    #[inline]
+    pub fn as_slice(this: &Self) -> &[T] {
+        this
+    }
+
+    /// Returns a mutable slice of the elements.
+    ///
+    /// This is equivalent to `&mut *this`, but can be named when `T` has a
+    /// method that would shadow it through
+    /// [`DerefMut`](core::ops::DerefMut).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_mut_slice(this)` instead of `this.as_mut_slice()`.
+    #[inline]
+    pub fn as_mut_slice(this: &mut Self) -> &mut [T] {
+        this
+    }
+
+    /// Returns an iterator over pinned mutable references to the elements.
+    ///
+    /// The elements of a pinned `Malloced<[T]>` are themselves pinned, since
+    /// the slice cannot be moved out of or reallocated without `T: Unpin`.
+    /// This allows traversing slices of `!Unpin` values, such as intrusive C
+    /// structures, without unpinning them.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::iter_pin_mut(this)` instead of `this.iter_pin_mut()`.
+    #[inline]
+    pub fn iter_pin_mut(this: Pin<&mut Self>) -> impl Iterator<Item = Pin<&mut T>> {
+        // SAFETY: Pinning is structural for the elements, so none of them are
+        // moved through the unpinned reference.
+        let slice: &mut [T] = unsafe { this.get_unchecked_mut() };
+        slice
+            .iter_mut()
+            .map(|elem| unsafe { Pin::new_unchecked(elem) })
+    }
+
+    /// Returns the number of elements that fit in the allocation, which may be
+    /// more than the length if the allocator over-provisioned memory.
+    ///
+    /// This is derived from [`usable_size`](#method.usable_size). For
+    /// zero-sized types, this is `usize::MAX`.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::capacity(this)` instead of `this.capacity()`.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        windows,
+    ))]
+    #[inline]
+    pub fn capacity(this: &Self) -> usize {
+        match mem::size_of::<T>() {
+            0 => usize::MAX,
+            size => Self::usable_size(this) / size,
+        }
+    }
+
+    /// Returns the slack at the end of the allocation, past the last element,
+    /// as a slice of uninitialized elements.
+    ///
+    /// Allocators often round requests up to a size class, so this can be
+    /// written to without an immediate `realloc`. The length can then be
+    /// extended over the written elements.
+    ///
+    /// The length of the returned slice is
+    /// <code>[capacity](#method.capacity)(this) - this.len()</code>, and is
+    /// always 0 for zero-sized types.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::spare_capacity_mut(this)` instead of
+    /// `this.spare_capacity_mut()`.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        windows,
+    ))]
+    #[inline]
+    pub fn spare_capacity_mut(this: &mut Self) -> &mut [MaybeUninit<T>] {
+        let len = this.len();
+        let spare = match mem::size_of::<T>() {
+            0 => 0,
+            _ => Self::capacity(this) - len,
+        };
+
+        unsafe {
+            let end = this.ptr.cast::<T>().as_ptr().add(len);
+            slice::from_raw_parts_mut(end.cast::<MaybeUninit<T>>(), spare)
+        }
+    }
+
+    /// Returns a reference to an element or subslice depending on the type of
+    /// index, or `None` if the index is out of bounds.
+    ///
+    /// - If given a position, returns a reference to the element at that
+    ///   position.
+    /// - If given a range, returns the subslice corresponding to that range.
+    ///
+    /// See [`slice::get`](https://doc.rust-lang.org/std/primitive.slice.html#method.get).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::get(this, index)` instead of `this.get(index)`.
+    #[inline]
+    pub fn get<I>(this: &Self, index: I) -> Option<&I::Output>
+    where
+        I: SliceIndex<[T]>,
+    {
+        Self::as_slice(this).get(index)
+    }
+
+    /// Returns a mutable reference to an element or subslice depending on the
+    /// type of index, or `None` if the index is out of bounds.
+    ///
+    /// See [`get`](#method.get).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::get_mut(this, index)` instead of `this.get_mut(index)`.
+    #[inline]
+    pub fn get_mut<I>(this: &mut Self, index: I) -> Option<&mut I::Output>
+    where
+        I: SliceIndex<[T]>,
+    {
+        Self::as_mut_slice(this).get_mut(index)
+    }
+
+    /// Returns the first element of the slice, or `None` if it is empty.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::first(this)` instead of `this.first()`.
+    #[inline]
+    pub fn first(this: &Self) -> Option<&T> {
+        Self::as_slice(this).first()
+    }
+
+    /// Returns the last element of the slice, or `None` if it is empty.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::last(this)` instead of `this.last()`.
+    #[inline]
+    pub fn last(this: &Self) -> Option<&T> {
+        Self::as_slice(this).last()
+    }
+
+    /// Returns the first element and the rest of the slice, or `None` if it is
+    /// empty.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::split_first(this)` instead of `this.split_first()`.
+    #[inline]
+    pub fn split_first(this: &Self) -> Option<(&T, &[T])> {
+        Self::as_slice(this).split_first()
+    }
+
+    /// Returns the last element and the rest of the slice, or `None` if it is
+    /// empty.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::split_last(this)` instead of `this.split_last()`.
+    #[inline]
+    pub fn split_last(this: &Self) -> Option<(&T, &[T])> {
+        Self::as_slice(this).split_last()
+    }
+
+    /// Rotates the slice in-place such that the first `mid` elements move to
+    /// the end.
+    ///
+    /// This never reallocates, so the pointer returned by
+    /// [`as_ptr`](#method.as_ptr) remains the same. This makes it safe to use
+    /// while C code holds onto the allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::rotate_left(this, mid)` instead of
+    /// `this.rotate_left(mid)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid` is greater than the length of the slice.
+    #[inline]
+    #[track_caller]
+    pub fn rotate_left(this: &mut Self, mid: usize) {
+        let len = this.len();
+        if mid > len {
+            rotate_fail(mid, len);
+        }
+        Self::as_mut_slice(this).rotate_left(mid);
+    }
+
+    /// Rotates the slice in-place such that the last `k` elements move to the
+    /// front.
+    ///
+    /// This never reallocates, so the pointer returned by
+    /// [`as_ptr`](#method.as_ptr) remains the same. This makes it safe to use
+    /// while C code holds onto the allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::rotate_right(this, k)` instead of
+    /// `this.rotate_right(k)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is greater than the length of the slice.
+    #[inline]
+    #[track_caller]
+    pub fn rotate_right(this: &mut Self, k: usize) {
+        let len = this.len();
+        if k > len {
+            rotate_fail(k, len);
+        }
+        Self::as_mut_slice(this).rotate_right(k);
+    }
+
+    /// Reverses the order of the elements in-place.
+    ///
+    /// This never reallocates, so the pointer returned by
+    /// [`as_ptr`](#method.as_ptr) remains the same. This makes it safe to use
+    /// while C code holds onto the allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::reverse(this)` instead of `this.reverse()`.
+    #[inline]
+    pub fn reverse(this: &mut Self) {
+        Self::as_mut_slice(this).reverse();
+    }
+
+    /// Sorts the slice in-place, preserving the order of equal elements.
+    ///
+    /// This never reallocates the `malloc`-ed buffer. Any scratch space
+    /// needed by the sort is allocated separately by the global allocator.
+    ///
+    /// See [`slice::sort`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort).
+    ///
+    /// This requires the `std` feature.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::sort(this)` instead of `this.sort()`.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn sort(this: &mut Self)
+    where
+        T: Ord,
+    {
+        Self::as_mut_slice(this).sort();
+    }
+
+    /// Sorts the slice in-place without preserving the order of equal
+    /// elements.
+    ///
+    /// This never allocates and never reallocates the `malloc`-ed buffer.
+    ///
+    /// See [`slice::sort_unstable`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_unstable).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::sort_unstable(this)` instead of `this.sort_unstable()`.
+    #[inline]
+    pub fn sort_unstable(this: &mut Self)
+    where
+        T: Ord,
+    {
+        Self::as_mut_slice(this).sort_unstable();
+    }
+
+    /// Binary searches the sorted slice for `x`.
+    ///
+    /// Returns `Ok` with the index of a matching element, or `Err` with the
+    /// index where `x` could be inserted while keeping the slice sorted.
+    ///
+    /// See [`slice::binary_search`](https://doc.rust-lang.org/std/primitive.slice.html#method.binary_search).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::binary_search(this, x)` instead of
+    /// `this.binary_search(x)`.
+    #[inline]
+    pub fn binary_search(this: &Self, x: &T) -> Result<usize, usize>
+    where
+        T: Ord,
+    {
+        Self::as_slice(this).binary_search(x)
+    }
+
+    /// Binary searches the sorted slice with a comparator function.
+    ///
+    /// `f` should return whether an element is `Less`, `Equal`, or `Greater`
+    /// than the target. Returns `Ok` with the index of a matching element, or
+    /// `Err` with the index where a matching element could be inserted while
+    /// keeping the slice sorted.
+    ///
+    /// See [`slice::binary_search_by`](https://doc.rust-lang.org/std/primitive.slice.html#method.binary_search_by).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::binary_search_by(this, f)` instead of
+    /// `this.binary_search_by(f)`.
+    #[inline]
+    pub fn binary_search_by<F>(this: &Self, f: F) -> Result<usize, usize>
+    where
+        F: FnMut(&T) -> Ordering,
+    {
+        Self::as_slice(this).binary_search_by(f)
+    }
+
+    /// Binary searches the slice, sorted by `f`, for the key `b`.
+    ///
+    /// Returns `Ok` with the index of a matching element, or `Err` with the
+    /// index where an element with key `b` could be inserted while keeping the
+    /// slice sorted.
+    ///
+    /// See [`slice::binary_search_by_key`](https://doc.rust-lang.org/std/primitive.slice.html#method.binary_search_by_key).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::binary_search_by_key(this, b, f)` instead of
+    /// `this.binary_search_by_key(b, f)`.
+    #[inline]
+    pub fn binary_search_by_key<B, F>(this: &Self, b: &B, f: F) -> Result<usize, usize>
+    where
+        F: FnMut(&T) -> B,
+        B: Ord,
+    {
+        Self::as_slice(this).binary_search_by_key(b, f)
+    }
+
+    /// Returns the index of the partition point of the slice according to
+    /// `pred`.
+    ///
+    /// The slice must be partitioned so that all elements for which `pred`
+    /// returns `true` come before all elements for which it returns `false`.
+    /// The result is the index of the first element of the second partition.
+    ///
+    /// See [`slice::partition_point`](https://doc.rust-lang.org/std/primitive.slice.html#method.partition_point).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::partition_point(this, pred)` instead of
+    /// `this.partition_point(pred)`.
+    #[inline]
+    pub fn partition_point<P>(this: &Self, pred: P) -> usize
+    where
+        P: FnMut(&T) -> bool,
+    {
+        Self::as_slice(this).partition_point(pred)
+    }
+
+    /// Returns an iterator over `chunk_size` elements of the slice at a time,
+    /// starting at the beginning of the slice.
+    ///
+    /// The last chunk will be shorter if `chunk_size` does not divide the
+    /// length of the slice.
+    ///
+    /// See [`slice::chunks`](https://doc.rust-lang.org/std/primitive.slice.html#method.chunks).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::chunks(this, chunk_size)` instead of
+    /// `this.chunks(chunk_size)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    #[inline]
+    #[track_caller]
+    pub fn chunks(this: &Self, chunk_size: usize) -> Chunks<'_, T> {
+        Self::as_slice(this).chunks(chunk_size)
+    }
+
+    /// Returns an iterator over `chunk_size` elements of the slice at a time,
+    /// starting at the beginning of the slice.
+    ///
+    /// If `chunk_size` does not divide the length of the slice, the last up to
+    /// `chunk_size - 1` elements are omitted and can be retrieved from
+    /// [`ChunksExact::remainder`](https://doc.rust-lang.org/std/slice/struct.ChunksExact.html#method.remainder).
+    ///
+    /// See [`slice::chunks_exact`](https://doc.rust-lang.org/std/primitive.slice.html#method.chunks_exact).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::chunks_exact(this, chunk_size)` instead of
+    /// `this.chunks_exact(chunk_size)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    #[inline]
+    #[track_caller]
+    pub fn chunks_exact(this: &Self, chunk_size: usize) -> ChunksExact<'_, T> {
+        Self::as_slice(this).chunks_exact(chunk_size)
+    }
+
+    /// Splits the slice into a slice of `N`-element arrays, starting at the
+    /// beginning of the slice, and a remainder slice with length strictly less
+    /// than `N`.
+    ///
+    /// This reinterprets the elements in place, which is sound because
+    /// `[T; N]` has the same alignment as `T` and a size of exactly `N` times
+    /// that of `T`.
+    ///
+    /// See [`slice::as_chunks`](https://doc.rust-lang.org/std/primitive.slice.html#method.as_chunks).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_chunks(this)` instead of `this.as_chunks()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let buf: Malloced<[u8]> = b"abcde".iter().copied().collect();
+    ///
+    /// let (chunks, remainder) = Malloced::as_chunks::<2>(&buf);
+    /// assert_eq!(chunks, &[*b"ab", *b"cd"]);
+    /// assert_eq!(remainder, b"e");
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn as_chunks<const N: usize>(this: &Self) -> (&[[T; N]], &[T]) {
+        assert!(N != 0, "chunk size must be non-zero");
+
+        let len = this.len() / N;
+        let (chunks, remainder) = Self::as_slice(this).split_at(len * N);
+
+        // SAFETY: `chunks` holds exactly `len * N` elements.
+        let chunks = unsafe { slice::from_raw_parts(chunks.as_ptr().cast(), len) };
+        (chunks, remainder)
+    }
+
+    /// Splits the slice into a mutable slice of `N`-element arrays, starting at
+    /// the beginning of the slice, and a mutable remainder slice with length
+    /// strictly less than `N`.
+    ///
+    /// See [`Malloced::as_chunks`](#method.as_chunks) and
+    /// [`slice::as_chunks_mut`](https://doc.rust-lang.org/std/primitive.slice.html#method.as_chunks_mut).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::as_chunks_mut(this)` instead of `this.as_chunks_mut()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is 0.
+    #[inline]
+    #[track_caller]
+    pub fn as_chunks_mut<const N: usize>(this: &mut Self) -> (&mut [[T; N]], &mut [T]) {
+        assert!(N != 0, "chunk size must be non-zero");
+
+        let len = this.len() / N;
+        let (chunks, remainder) = Self::as_mut_slice(this).split_at_mut(len * N);
+
+        // SAFETY: `chunks` holds exactly `len * N` elements.
+        let chunks = unsafe { slice::from_raw_parts_mut(chunks.as_mut_ptr().cast(), len) };
+        (chunks, remainder)
+    }
+
+    /// Returns an iterator over all contiguous windows of length `size`.
+    ///
+    /// See [`slice::windows`](https://doc.rust-lang.org/std/primitive.slice.html#method.windows).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::windows(this, size)` instead of `this.windows(size)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    #[inline]
+    #[track_caller]
+    pub fn windows(this: &Self, size: usize) -> Windows<'_, T> {
+        Self::as_slice(this).windows(size)
+    }
+
+    /// Returns an iterator over the maximal runs of elements where each pair of
+    /// adjacent elements satisfies `pred`.
+    ///
+    /// This mirrors
+    /// [`slice::chunk_by`](https://doc.rust-lang.org/std/primitive.slice.html#method.chunk_by),
+    /// which is newer than this crate's MSRV.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::chunk_by(this, pred)` instead of `this.chunk_by(pred)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let buf: Malloced<[u8]> = vec![1, 1, 2, 3, 3].into_iter().collect();
+    ///
+    /// let mut runs = Malloced::chunk_by(&buf, |a, b| a == b);
+    /// assert_eq!(runs.next(), Some(&[1, 1][..]));
+    /// assert_eq!(runs.next(), Some(&[2][..]));
+    /// assert_eq!(runs.next(), Some(&[3, 3][..]));
+    /// assert_eq!(runs.next(), None);
+    /// ```
+    #[inline]
+    pub fn chunk_by<F>(this: &Self, pred: F) -> ChunkBy<'_, T, F>
+    where
+        F: FnMut(&T, &T) -> bool,
+    {
+        ChunkBy {
+            slice: Self::as_slice(this),
+            pred,
+        }
+    }
+
+    /// Replaces the slice pointer with one to `len` elements at `data`.
+    ///
+    /// # Safety
+    ///
+    /// `data` must be the `malloc`-ed allocation owned by `this`, holding `len`
+    /// initialized elements.
+    #[inline]
+    unsafe fn set_raw_parts(this: &mut Self, data: NonNull<T>, len: usize) {
+        this.ptr = NonNull::new_unchecked(ptr::slice_from_raw_parts_mut(data.as_ptr(), len));
+    }
+
+    /// Shrinks the allocation via `realloc` after the slice has been truncated
+    /// to `len` elements.
+    ///
+    /// Shrinking is best-effort and never panics. If `realloc` fails or `T` is
+    /// over-aligned for `realloc`, `this` keeps its current allocation.
+    fn shrink_allocation(this: &mut Self, len: usize) {
+        debug_assert!(len <= this.len());
+
+        if mem::size_of::<T>() == 0 {
+            return;
+        }
+
+        if let Ok(data) = try_realloc_array(this.ptr.cast::<T>(), len) {
+            unsafe { Self::set_raw_parts(this, data, len) };
+        }
+    }
+
+    /// Shortens the slice to `len` elements, dropping the rest and shrinking
+    /// the allocation via `realloc`.
+    ///
+    /// Unlike [`Vec::truncate`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.truncate),
+    /// this returns the freed memory to the allocator. If the slice has `len`
+    /// or fewer elements, this does nothing.
+    ///
+    /// Shrinking the allocation is best-effort. If `realloc` fails or `T` is
+    /// over-aligned for `realloc`, `this` keeps its current allocation with
+    /// `len` elements.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::shrink_to(this, len)` instead of `this.shrink_to(len)`.
+    pub fn shrink_to(this: &mut Self, len: usize) {
+        let old_len = this.len();
+        if old_len <= len {
+            return;
+        }
+
+        let base = this.ptr.cast::<T>();
+        unsafe {
+            // Set the length first so that a panicking destructor cannot cause
+            // the tail to be dropped twice.
+            Self::set_raw_parts(this, base, len);
+
+            let tail = ptr::slice_from_raw_parts_mut(base.as_ptr().add(len), old_len - len);
+            ptr::drop_in_place(tail);
+        }
+
+        Self::shrink_allocation(this, len);
+    }
+
+    /// Sets the length of the slice to `new_len` without touching the
+    /// allocation.
+    ///
+    /// This is a low-level operation for when the contents are written through
+    /// a raw pointer, such as by a C function that fills part of a buffer. It
+    /// mirrors
+    /// [`Vec::set_len`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.set_len).
+    /// Shrinking with this does not drop the elements past `new_len`, and does
+    /// not return their memory to the allocator; prefer
+    /// [`Malloced::shrink_to`](#method.shrink_to) for that.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::set_len(this, new_len)` instead of
+    /// `this.set_len(new_len)`.
+    ///
+    /// # Safety
+    ///
+    /// - The allocation must be large enough to hold `new_len` elements of
+    ///   `T`. Since a `Malloced` slice does not track a separate capacity, this
+    ///   is the length it was allocated with, not the current length.
+    ///
+    /// - The elements at `0..new_len` must be initialized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let (ptr, _) = Malloced::into_raw_parts(Malloced::<[u32]>::new_uninit_slice(8));
+    /// let mut buf = unsafe { Malloced::slice_from_raw_parts(ptr as *mut u32, 0) };
+    ///
+    /// // Stands in for a C function that fills part of the buffer.
+    /// unsafe {
+    ///     let ptr = Malloced::as_mut_ptr(&mut buf) as *mut u32;
+    ///     ptr.write(1);
+    ///     ptr.add(1).write(2);
+    ///
+    ///     Malloced::set_len(&mut buf, 2);
+    /// }
+    /// assert_eq!(&*buf, &[1, 2]);
+    /// ```
+    #[inline]
+    pub unsafe fn set_len(this: &mut Self, new_len: usize) {
+        Self::set_raw_parts(this, this.ptr.cast::<T>(), new_len);
+    }
+
+    /// Retains only the elements for which `f` returns `true`, removing the
+    /// rest and shrinking the allocation via `realloc`.
+    ///
+    /// This visits each element exactly once in the original order, and
+    /// preserves the order of the retained elements. This mirrors
+    /// [`Vec::retain`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.retain).
+    ///
+    /// If `f` panics, the elements that have not yet been visited are kept,
+    /// and no element is dropped twice.
+    ///
+    /// Shrinking the allocation is best-effort. If `realloc` fails or `T` is
+    /// over-aligned for `realloc`, `this` keeps its current allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::retain(this, f)` instead of `this.retain(f)`.
+    pub fn retain<F>(this: &mut Self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        // Removed elements leave a gap that retained elements are shifted
+        // back into. If `f` or a destructor panics, the guard shifts the
+        // unvisited tail back and fixes the length.
+        struct Guard<'a, T> {
+            this: &'a mut Malloced<[T]>,
+            base: NonNull<T>,
+            len: usize,
+            processed: usize,
+            deleted: usize,
+        }
+
+        impl<T> Drop for Guard<'_, T> {
+            fn drop(&mut self) {
+                unsafe {
+                    let base = self.base.as_ptr();
+                    if self.deleted > 0 {
+                        ptr::copy(
+                            base.add(self.processed),
+                            base.add(self.processed - self.deleted),
+                            self.len - self.processed,
+                        );
+                    }
+                    Malloced::set_raw_parts(self.this, self.base, self.len - self.deleted);
+                }
+            }
+        }
+
+        let len = this.len();
+        let base = this.ptr.cast::<T>();
+
+        let mut guard = Guard {
+            this,
+            base,
+            len,
+            processed: 0,
+            deleted: 0,
+        };
+
+        while guard.processed < len {
+            unsafe {
+                let cur = base.as_ptr().add(guard.processed);
+                if !f(&*cur) {
+                    guard.processed += 1;
+                    guard.deleted += 1;
+                    ptr::drop_in_place(cur);
+                } else {
+                    if guard.deleted > 0 {
+                        ptr::copy_nonoverlapping(cur, cur.sub(guard.deleted), 1);
+                    }
+                    guard.processed += 1;
+                }
+            }
+        }
+
+        let deleted = guard.deleted;
+        drop(guard);
+
+        if deleted > 0 {
+            let new_len = this.len();
+            Self::shrink_allocation(this, new_len);
+        }
+    }
+
+    /// Removes consecutive repeated elements, dropping them and shrinking the
+    /// allocation via `realloc`.
+    ///
+    /// If the slice is sorted, this removes all duplicates.
+    ///
+    /// Shrinking the allocation is best-effort. If `realloc` fails or `T` is
+    /// over-aligned for `realloc`, `this` keeps its current allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::dedup(this)` instead of `this.dedup()`.
+    #[inline]
+    pub fn dedup(this: &mut Self)
+    where
+        T: PartialEq,
+    {
+        Self::dedup_by(this, |a, b| a == b);
+    }
+
+    /// Removes consecutive elements that resolve to the same key, dropping
+    /// them and shrinking the allocation via `realloc`.
+    ///
+    /// Shrinking the allocation is best-effort. If `realloc` fails or `T` is
+    /// over-aligned for `realloc`, `this` keeps its current allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::dedup_by_key(this, key)` instead of
+    /// `this.dedup_by_key(key)`.
+    #[inline]
+    pub fn dedup_by_key<K, F>(this: &mut Self, mut key: F)
+    where
+        F: FnMut(&mut T) -> K,
+        K: PartialEq,
+    {
+        Self::dedup_by(this, |a, b| key(a) == key(b));
+    }
+
+    /// Removes consecutive elements that satisfy `same_bucket`, dropping them
+    /// and shrinking the allocation via `realloc`.
+    ///
+    /// `same_bucket` is passed each element and the last retained element
+    /// before it, in that order. If it returns `true`, the element is removed.
+    /// This mirrors
+    /// [`Vec::dedup_by`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.dedup_by).
+    ///
+    /// Shrinking the allocation is best-effort. If `realloc` fails or `T` is
+    /// over-aligned for `realloc`, `this` keeps its current allocation.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::dedup_by(this, same_bucket)` instead of
+    /// `this.dedup_by(same_bucket)`.
+    pub fn dedup_by<F>(this: &mut Self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        // Elements in `[write, read)` have been removed. If `same_bucket` or a
+        // destructor panics, the guard shifts the unvisited tail back and
+        // fixes the length.
+        struct Guard<'a, T> {
+            this: &'a mut Malloced<[T]>,
+            base: NonNull<T>,
+            len: usize,
+            read: usize,
+            write: usize,
+        }
+
+        impl<T> Drop for Guard<'_, T> {
+            fn drop(&mut self) {
+                unsafe {
+                    let base = self.base.as_ptr();
+                    ptr::copy(
+                        base.add(self.read),
+                        base.add(self.write),
+                        self.len - self.read,
+                    );
+                    let len = self.len - (self.read - self.write);
+                    Malloced::set_raw_parts(self.this, self.base, len);
+                }
+            }
+        }
+
+        let len = this.len();
+        if len <= 1 {
+            return;
+        }
+
+        let base = this.ptr.cast::<T>();
+
+        let mut guard = Guard {
+            this,
+            base,
+            len,
+            read: 1,
+            write: 1,
+        };
+
+        while guard.read < len {
+            unsafe {
+                let read = base.as_ptr().add(guard.read);
+                let prev = base.as_ptr().add(guard.write - 1);
+                if same_bucket(&mut *read, &mut *prev) {
+                    guard.read += 1;
+                    ptr::drop_in_place(read);
+                } else {
+                    ptr::copy(read, base.as_ptr().add(guard.write), 1);
+                    guard.write += 1;
+                    guard.read += 1;
+                }
+            }
+        }
+
+        let removed = guard.read != guard.write;
+        drop(guard);
+
+        if removed {
+            let new_len = this.len();
+            Self::shrink_allocation(this, new_len);
+        }
+    }
+
+    /// Splits the slice into two owned halves at `mid`.
+    ///
+    /// The first half contains the elements in `[0, mid)` and the second
+    /// contains the elements in `[mid, len)`. Since a single `malloc`-ed
+    /// allocation cannot be freed in two pieces, each half is copied into a
+    /// new allocation and the original allocation is freed.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::split_at(this, mid)` instead of `this.split_at(mid)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len` or if `malloc` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use malloced::Malloced;
+    ///
+    /// let buf: Malloced<[u8]> = (1..=5).collect();
+    ///
+    /// let (left, right) = Malloced::split_at(buf, 2);
+    /// assert_eq!(&*left, &[1, 2]);
+    /// assert_eq!(&*right, &[3, 4, 5]);
+    /// ```
+    #[track_caller]
+    pub fn split_at(this: Self, mid: usize) -> (Self, Self)
+    where
+        T: Copy,
+    {
+        let len = this.len();
+        if mid > len {
+            split_at_fail(mid, len);
+        }
+
+        let copy = |src: &[T]| unsafe {
+            let buf = malloc_array::<T>(src.len());
+            ptr::copy_nonoverlapping(src.as_ptr(), buf.as_ptr(), src.len());
+            Malloced::slice_from_raw_parts(buf.as_ptr(), src.len())
+        };
+
+        let (left, right) = Self::as_slice(&this).split_at(mid);
+        (copy(left), copy(right))
+    }
+
+    /// Copies the elements of all `slices`, in order, into one new `malloc`-ed
+    /// allocation.
+    ///
+    /// The total length is computed up front, so this makes exactly one
+    /// allocation. This is useful for gathering scattered data into a single
+    /// contiguous buffer for a C call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the total length overflows or if `malloc` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let buf = Malloced::<[u8]>::from_slices(&[b"GET ", b"/index.html", b" HTTP/1.1"]);
+    /// assert_eq!(&*buf, b"GET /index.html HTTP/1.1");
+    /// ```
+    pub fn from_slices(slices: &[&[T]]) -> Self
+    where
+        T: Copy,
+    {
+        let len = slices
+            .iter()
+            .fold(0usize, |len, slice| match len.checked_add(slice.len()) {
+                Some(len) => len,
+                None => capacity_overflow(),
+            });
+
+        let buf = malloc_array::<T>(len);
+        let mut offset = 0;
+
+        unsafe {
+            for slice in slices {
+                ptr::copy_nonoverlapping(slice.as_ptr(), buf.as_ptr().add(offset), slice.len());
+                offset += slice.len();
+            }
+            Self::slice_from_raw_parts(buf.as_ptr(), len)
+        }
+    }
+
+    /// Copies the elements into a new `malloc`-ed allocation with the same
+    /// usable size as the source.
+    ///
+    /// This keeps round-tripped buffers in the same allocator size class. On
+    /// platforms where [`Malloced::usable_size`](#method.usable_size) is
+    /// available, the new allocation requests the full usable size of `this`.
+    /// Elsewhere, this only requests the size of the elements, like
+    /// [`Clone`](https://doc.rust-lang.org/std/clone/trait.Clone.html).
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::clone_with_capacity(this)` instead of
+    /// `this.clone_with_capacity()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails or does not return memory aligned for `T`.
+    pub fn clone_with_capacity(this: &Self) -> Self
+    where
+        T: Copy,
+    {
+        let len = this.len();
+        let size = mem::size_of_val::<[T]>(this);
+
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "ios",
+            windows,
+        ))]
+        let size = match size {
+            0 => 0,
+            _ => size.max(Self::usable_size(this)),
+        };
+
+        let buf = malloc_aligned::<T>(size);
+        unsafe {
+            ptr::copy_nonoverlapping(this.as_ptr(), buf.as_ptr(), len);
+            Self::slice_from_raw_parts(buf.as_ptr(), len)
+        }
+    }
+
+    /// Moves the elements of `other` onto the end of `this`, growing its
+    /// allocation via `realloc`.
+    ///
+    /// Unlike concatenating into a new buffer, only `this` is resized, and
+    /// `other` is `free`-d afterward. The `realloc` may move `this` to a new
+    /// address.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::append(this, other)` instead of `this.append(other)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new length overflows or if `realloc` fails, in which case
+    /// `this` is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use malloced::Malloced;
+    /// let mut buf: Malloced<[u8]> = vec![1, 2].into_iter().collect();
+    /// let tail: Malloced<[u8]> = vec![3, 4, 5].into_iter().collect();
+    ///
+    /// Malloced::append(&mut buf, tail);
+    /// assert_eq!(&*buf, &[1, 2, 3, 4, 5]);
+    /// ```
+    pub fn append(this: &mut Self, other: Self)
+    where
+        T: Copy,
+    {
+        if other.is_empty() {
+            return;
+        }
+
+        let len = this.len();
+        let new_len = match len.checked_add(other.len()) {
+            Some(new_len) => new_len,
+            None => capacity_overflow(),
+        };
+
+        unsafe {
+            let data = match mem::size_of::<T>() {
+                0 => this.ptr.cast::<T>(),
+                _ => realloc_array(this.ptr.cast::<T>(), new_len),
+            };
+            ptr::copy_nonoverlapping(other.as_ptr(), data.as_ptr().add(len), other.len());
+            Self::set_raw_parts(this, data, new_len);
+        }
+    }
+
+    /// Clones the elements into a [`Box<[T]>`](https://doc.rust-lang.org/std/boxed/struct.Box.html)
+    /// allocated by Rust's global allocator.
+    ///
+    /// The elements must be copied since memory from `malloc` cannot be
+    /// released by the global allocator.
+    ///
+    /// This requires the `alloc` feature.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::to_boxed_slice(this)` instead of
+    /// `this.to_boxed_slice()`.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn to_boxed_slice(this: &Self) -> Box<[T]>
+    where
+        T: Clone,
+    {
+        Box::from(Self::as_slice(this))
+    }
+
+    /// Moves the elements of a [`Box<[T]>`](https://doc.rust-lang.org/std/boxed/struct.Box.html)
+    /// into a new `malloc`-ed allocation.
+    ///
+    /// The elements must be moved since memory from Rust's global allocator
+    /// cannot be passed to `free`. They are moved without being cloned or
+    /// dropped, and the box's memory is then deallocated.
+    ///
+    /// This requires the `alloc` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` fails, in which case `boxed` is dropped.
+    #[cfg(feature = "alloc")]
+    pub fn from_boxed_slice(boxed: Box<[T]>) -> Self {
+        let len = boxed.len();
+        let buf = malloc_array::<T>(len);
+
+        unsafe {
+            let src = Box::into_raw(boxed) as *mut T;
+            ptr::copy_nonoverlapping(src, buf.as_ptr(), len);
+
+            // Deallocate the box without dropping the moved elements.
+            drop(Box::from_raw(
+                ptr::slice_from_raw_parts_mut(src, len) as *mut [mem::ManuallyDrop<T>]
+            ));
+
+            Self::slice_from_raw_parts(buf.as_ptr(), len)
+        }
+    }
+
+    /// Copies the elements into a [`Vec<T>`](https://doc.rust-lang.org/std/vec/struct.Vec.html)
+    /// allocated by Rust's global allocator, and frees the `malloc`-ed memory.
+    ///
+    /// This is equivalent to `Vec::from(this)`, which copies the elements with
+    /// a single `memcpy`.
+    ///
+    /// This requires the `alloc` feature.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::into_vec_copied(this)` instead of
+    /// `this.into_vec_copied()`.
+    #[cfg(feature = "alloc")]
+    pub fn into_vec_copied(this: Self) -> Vec<T>
+    where
+        T: Copy,
+    {
+        Vec::from(this)
+    }
+
+    /// Reinterprets the slice's allocation as a slice of `U` without copying,
+    /// if its size and alignment allow it.
+    ///
+    /// This succeeds only if the byte length of the slice is a multiple of
+    /// `size_of::<U>()` and the data is aligned for `U`. Otherwise, `this` is
+    /// returned back as an error. If either type is zero-sized, both must be.
+    ///
+    /// See `cast_slice` under the `bytemuck` feature for a safe alternative
+    /// that requires [`Pod`](https://docs.rs/bytemuck/1/bytemuck/trait.Pod.html)
+    /// types.
+    ///
+    /// # Safety
+    ///
+    /// The bytes of the elements must be valid values of `U`. In particular,
+    /// `T` must not contain padding or pointers that `U` would expose, and any
+    /// bit pattern produced by the elements must be a valid `U`. The elements
+    /// are not dropped as `T`, but as `U` once the result is dropped.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::try_cast_slice(this)` instead of
+    /// `this.try_cast_slice()`.
+    pub unsafe fn try_cast_slice<U>(this: Self) -> Result<Malloced<[U]>, Self> {
+        let (t_size, u_size) = (mem::size_of::<T>(), mem::size_of::<U>());
+
+        let len = match (t_size, u_size) {
+            (0, 0) => this.len(),
+            (0, _) | (_, 0) => return Err(this),
+            _ => {
+                let bytes = t_size * this.len();
+                if bytes % u_size != 0 {
+                    return Err(this);
+                }
+                bytes / u_size
+            }
+        };
+
+        if this.as_ptr() as usize % mem::align_of::<U>() != 0 {
+            return Err(this);
+        }
+
+        let (ptr, _) = Self::into_raw_parts(this);
+        Ok(Malloced::slice_from_raw_parts(ptr as *mut U, len))
+    }
+
+    /// Returns an iterator that removes and yields the elements for which
+    /// `pred` returns `true`.
+    ///
+    /// The retained elements keep their order and are compacted towards the
+    /// start of the slice. Once the iterator is dropped, the allocation is
+    /// shrunk via `realloc`. This mirrors
+    /// [`Vec::extract_if`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.extract_if).
+    ///
+    /// If the iterator is dropped before being fully consumed, the elements
+    /// that have not yet been visited are retained. If the iterator is leaked,
+    /// `this` may appear empty and its elements may be leaked, but its memory
+    /// is still freed.
+    ///
+    /// Note: this is an associated function, which means that you have to call
+    /// it as `Malloced::extract_if(this, pred)` instead of
+    /// `this.extract_if(pred)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use malloced::Malloced;
+    ///
+    /// let mut buf: Malloced<[u32]> = (1..=6).collect();
+    ///
+    /// let odds: Vec<u32> = Malloced::extract_if(&mut buf, |x| *x % 2 == 1).collect();
+    /// assert_eq!(odds, [1, 3, 5]);
+    /// assert_eq!(&*buf, &[2, 4, 6]);
+    /// ```
+    pub fn extract_if<F>(this: &mut Self, pred: F) -> ExtractIf<'_, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let old_len = this.len();
+        let base = this.ptr.cast::<T>();
+
+        // Guard against the iterator being leaked while elements are moved.
+        unsafe { Self::set_raw_parts(this, base, 0) };
+
+        ExtractIf {
+            this,
+            base,
+            idx: 0,
+            del: 0,
+            old_len,
+            pred,
+        }
+    }
+}
+
+/// An iterator that removes the elements of a
+/// <code>[Malloced](crate::Malloced)<[\[T\]](prim@slice)></code> that match
+/// a predicate.
+///
+/// This is created by
+/// <code>[Malloced::extract_if](crate::Malloced::extract_if)</code>.
+pub struct ExtractIf<'a, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    this: &'a mut Malloced<[T]>,
+    base: NonNull<T>,
+    /// The index of the next element to visit.
+    idx: usize,
+    /// The number of elements removed so far.
+    del: usize,
+    old_len: usize,
+    pred: F,
+}
+
+impl<T, F> ExtractIf<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    /// Returns the elements that have not yet been visited.
+    #[inline]
+    fn unvisited(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.base.as_ptr().add(self.idx), self.old_len - self.idx) }
+    }
+}
+
+impl<T, F> Iterator for ExtractIf<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while self.idx < self.old_len {
+            unsafe {
+                let cur = self.base.as_ptr().add(self.idx);
+                let extract = (self.pred)(&mut *cur);
+
+                // Advance only after `pred` returns so that if it panics, the
+                // current element is retained rather than leaked.
+                self.idx += 1;
+
+                if extract {
+                    self.del += 1;
+                    return Some(ptr::read(cur));
+                } else if self.del > 0 {
+                    ptr::copy_nonoverlapping(cur, cur.sub(self.del), 1);
+                }
+            }
+        }
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.old_len - self.idx))
+    }
+}
+
+impl<T, F> FusedIterator for ExtractIf<'_, T, F> where F: FnMut(&mut T) -> bool {}
+
+impl<T: fmt::Debug, F> fmt::Debug for ExtractIf<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ExtractIf").field(&self.unvisited()).finish()
+    }
+}
+
+impl<T, F> Drop for ExtractIf<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    fn drop(&mut self) {
+        unsafe {
+            let base = self.base.as_ptr();
+            if self.del > 0 {
+                ptr::copy(
+                    base.add(self.idx),
+                    base.add(self.idx - self.del),
+                    self.old_len - self.idx,
+                );
+            }
+
+            let len = self.old_len - self.del;
+            Malloced::set_raw_parts(self.this, self.base, len);
+        }
+
+        // This may run while unwinding from a panic in the predicate, which
+        // is fine since shrinking never panics.
+        if self.del > 0 {
+            let len = self.this.len();
+            Malloced::shrink_allocation(self.this, len);
+        }
+    }
+}
+
+/// An iterator over the runs of a
+/// <code>[Malloced](crate::Malloced)<[\[T\]](prim@slice)></code> whose
+/// adjacent elements satisfy a predicate.
+///
+/// This is created by
+/// <code>[Malloced::chunk_by](crate::Malloced::chunk_by)</code>.
+pub struct ChunkBy<'a, T, F> {
+    slice: &'a [T],
+    pred: F,
+}
+
+impl<'a, T, F> Iterator for ChunkBy<'a, T, F>
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    type Item = &'a [T];
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.slice.is_empty() {
+            return None;
+        }
+
+        let mut len = 1;
+        while len < self.slice.len() && (self.pred)(&self.slice[len - 1], &self.slice[len]) {
+            len += 1;
+        }
+
+        let (run, rest) = self.slice.split_at(len);
+        self.slice = rest;
+        Some(run)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.slice.len() {
+            0 => (0, Some(0)),
+            len => (1, Some(len)),
+        }
+    }
+}
+
+impl<'a, T, F> DoubleEndedIterator for ChunkBy<'a, T, F>
+where
+    F: FnMut(&T, &T) -> bool,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.slice.is_empty() {
+            return None;
+        }
+
+        let mut start = self.slice.len() - 1;
+        while start > 0 && (self.pred)(&self.slice[start - 1], &self.slice[start]) {
+            start -= 1;
+        }
+
+        let (rest, run) = self.slice.split_at(start);
+        self.slice = rest;
+        Some(run)
+    }
+}
+
+impl<T, F> FusedIterator for ChunkBy<'_, T, F> where F: FnMut(&T, &T) -> bool {}
+
+impl<T: fmt::Debug, F> fmt::Debug for ChunkBy<'_, T, F> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ChunkBy").field(&self.slice).finish()
+    }
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn rotate_fail(mid: usize, len: usize) -> ! {
+    panic!("rotation amount (is {}) should be <= len (is {})", mid, len)
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn split_at_fail(mid: usize, len: usize) -> ! {
+    panic!("split index (is {}) should be <= len (is {})", mid, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Malloced;
+    use alloc::vec::Vec;
+    use core::cell::Cell;
+
+    #[derive(Clone, Debug)]
+    struct DropCounter<'a>(&'a Cell<usize>, u32);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    mod as_slice {
+        use super::*;
+
+        #[test]
+        fn as_slice() {
+            let mut buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            assert_eq!(Malloced::as_slice(&buf), &[1, 2, 3]);
+
+            Malloced::as_mut_slice(&mut buf)[0] = 4;
+            assert_eq!(Malloced::as_slice(&buf), &[4, 2, 3]);
+        }
+    }
+
+    #[cfg(feature = "pin")]
+    mod pin {
+        use super::*;
+        use core::{marker::PhantomPinned, pin::Pin, ptr};
+
+        /// Points to its own `value` once pinned.
+        #[derive(Clone)]
+        struct SelfRef {
+            value: u32,
+            this: *const u32,
+            _pin: PhantomPinned,
+        }
+
+        #[test]
+        fn iter_pin_mut() {
+            let node = SelfRef {
+                value: 0,
+                this: ptr::null(),
+                _pin: PhantomPinned,
+            };
+            let mut buf =
+                Malloced::<[SelfRef]>::alloc(&[node.clone(), node.clone(), node]).unwrap();
+
+            // Shadow `buf` so that it cannot be accessed unpinned.
+            let mut buf = unsafe { Pin::new_unchecked(&mut buf) };
+
+            for (i, node) in Malloced::iter_pin_mut(buf.as_mut()).enumerate() {
+                let node = unsafe { node.get_unchecked_mut() };
+                node.value = i as u32;
+                node.this = &node.value;
+            }
+
+            for node in Malloced::iter_pin_mut(buf.as_mut()) {
+                assert_eq!(node.this, &node.value as *const u32);
+            }
+
+            let values: Vec<u32> = buf.iter().map(|node| unsafe { *node.this }).collect();
+            assert_eq!(values, [0, 1, 2]);
+        }
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        windows,
+    ))]
+    mod capacity {
+        use super::*;
+
+        #[test]
+        fn capacity() {
+            let mut buf = Malloced::<[u32]>::alloc(&[1, 2, 3]).unwrap();
+            let cap = Malloced::capacity(&buf);
+            assert!(cap >= buf.len());
+            assert_eq!(Malloced::spare_capacity_mut(&mut buf).len(), cap - 3);
+            assert_eq!(&*buf, &[1, 2, 3]);
+        }
+
+        #[test]
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        fn spare_small() {
+            // glibc's smallest chunk has several words of usable space.
+            let mut buf = Malloced::<[u8]>::alloc(&[1]).unwrap();
+            let ptr = Malloced::as_ptr(&buf) as *const u8;
+
+            let spare = Malloced::spare_capacity_mut(&mut buf);
+            assert!(!spare.is_empty());
+            assert_eq!(spare.as_ptr() as *const u8, ptr.wrapping_add(1));
+
+            for byte in spare {
+                *byte = core::mem::MaybeUninit::new(0xff);
+            }
+            assert_eq!(&*buf, &[1]);
+        }
+
+        #[test]
+        fn zst() {
+            let mut buf = Malloced::<[()]>::alloc(&[(), ()]).unwrap();
+            assert_eq!(Malloced::capacity(&buf), usize::MAX);
+            assert!(Malloced::spare_capacity_mut(&mut buf).is_empty());
+        }
+    }
+
+    mod get {
+        use super::*;
+
+        #[test]
+        fn in_bounds() {
+            let mut buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            assert_eq!(Malloced::get(&buf, 0), Some(&1));
+            assert_eq!(Malloced::get(&buf, 2), Some(&3));
+
+            *Malloced::get_mut(&mut buf, 1).unwrap() = 5;
+            assert_eq!(&*buf, &[1, 5, 3]);
+        }
+
+        #[test]
+        fn out_of_bounds() {
+            let mut buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            assert_eq!(Malloced::get(&buf, 3), None);
+            assert_eq!(Malloced::get(&buf, 2..4), None);
+            assert_eq!(Malloced::get_mut(&mut buf, usize::MAX), None);
+            assert_eq!(Malloced::get_mut(&mut buf, ..4), None);
+        }
+
+        #[test]
+        fn range() {
+            let mut buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4]).unwrap();
+            assert_eq!(Malloced::get(&buf, 1..3), Some(&[2, 3][..]));
+            assert_eq!(Malloced::get(&buf, ..2), Some(&[1, 2][..]));
+            assert_eq!(Malloced::get(&buf, 2..), Some(&[3, 4][..]));
+            assert_eq!(Malloced::get(&buf, ..=0), Some(&[1][..]));
+            assert_eq!(Malloced::get(&buf, ..), Some(&[1, 2, 3, 4][..]));
+
+            Malloced::get_mut(&mut buf, 2..).unwrap().fill(0);
+            assert_eq!(&*buf, &[1, 2, 0, 0]);
+        }
+    }
+
+    mod first_last {
+        use super::*;
+
+        #[test]
+        fn empty() {
+            let buf = Malloced::<[u8]>::alloc(&[]).unwrap();
+            assert_eq!(Malloced::first(&buf), None);
+            assert_eq!(Malloced::last(&buf), None);
+            assert_eq!(Malloced::split_first(&buf), None);
+            assert_eq!(Malloced::split_last(&buf), None);
+        }
+
+        #[test]
+        fn single() {
+            let buf = Malloced::<[u8]>::alloc(&[1]).unwrap();
+            assert_eq!(Malloced::first(&buf), Some(&1));
+            assert_eq!(Malloced::last(&buf), Some(&1));
+            assert_eq!(Malloced::split_first(&buf), Some((&1, &[][..])));
+            assert_eq!(Malloced::split_last(&buf), Some((&1, &[][..])));
+        }
+
+        #[test]
+        fn many() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            assert_eq!(Malloced::first(&buf), Some(&1));
+            assert_eq!(Malloced::last(&buf), Some(&3));
+            assert_eq!(Malloced::split_first(&buf), Some((&1, &[2, 3][..])));
+            assert_eq!(Malloced::split_last(&buf), Some((&3, &[1, 2][..])));
+        }
+    }
+
+    mod rotate {
+        use super::*;
+
+        #[test]
+        fn left() {
+            let mut buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4, 5]).unwrap();
+            let ptr = Malloced::as_ptr(&buf);
+
+            Malloced::rotate_left(&mut buf, 2);
+            assert_eq!(&*buf, &[3, 4, 5, 1, 2]);
+            assert_eq!(Malloced::as_ptr(&buf), ptr);
+
+            Malloced::rotate_left(&mut buf, 5);
+            assert_eq!(&*buf, &[3, 4, 5, 1, 2]);
+            assert_eq!(Malloced::as_ptr(&buf), ptr);
+        }
+
+        #[test]
+        fn right() {
+            let mut buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4, 5]).unwrap();
+            let ptr = Malloced::as_ptr(&buf);
+
+            Malloced::rotate_right(&mut buf, 2);
+            assert_eq!(&*buf, &[4, 5, 1, 2, 3]);
+            assert_eq!(Malloced::as_ptr(&buf), ptr);
+
+            Malloced::rotate_right(&mut buf, 0);
+            assert_eq!(&*buf, &[4, 5, 1, 2, 3]);
+            assert_eq!(Malloced::as_ptr(&buf), ptr);
+        }
+
+        #[test]
+        #[should_panic = "rotation amount (is 6) should be <= len (is 5)"]
+        fn left_out_of_bounds() {
+            let mut buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4, 5]).unwrap();
+            Malloced::rotate_left(&mut buf, 6);
+        }
+
+        #[test]
+        #[should_panic = "rotation amount (is 1) should be <= len (is 0)"]
+        fn right_out_of_bounds() {
+            let mut buf = Malloced::<[u8]>::alloc(&[]).unwrap();
+            Malloced::rotate_right(&mut buf, 1);
+        }
+    }
+
+    mod reverse {
+        use super::*;
+
+        #[test]
+        fn reverse() {
+            let mut buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4, 5]).unwrap();
+            let ptr = Malloced::as_ptr(&buf);
+
+            Malloced::reverse(&mut buf);
+            assert_eq!(&*buf, &[5, 4, 3, 2, 1]);
+            assert_eq!(Malloced::as_ptr(&buf), ptr);
+
+            Malloced::reverse(&mut buf);
+            assert_eq!(&*buf, &[1, 2, 3, 4, 5]);
+            assert_eq!(Malloced::as_ptr(&buf), ptr);
+        }
+
+        #[test]
+        fn empty() {
+            let mut buf = Malloced::<[u8]>::alloc(&[]).unwrap();
+            Malloced::reverse(&mut buf);
+            assert_eq!(&*buf, &[]);
+        }
+    }
+
+    mod sort {
+        use super::*;
+
+        #[test]
+        #[cfg(feature = "std")]
+        fn stable() {
+            let mut buf = Malloced::<[i32]>::alloc(&[3, -1, 4, 1, -5, 9, 2, 6]).unwrap();
+            let ptr = Malloced::as_ptr(&buf);
+
+            Malloced::sort(&mut buf);
+            assert_eq!(&*buf, &[-5, -1, 1, 2, 3, 4, 6, 9]);
+            assert_eq!(Malloced::as_ptr(&buf), ptr);
+        }
+
+        #[test]
+        fn unstable() {
+            let mut buf = Malloced::<[i32]>::alloc(&[3, -1, 4, 1, -5, 9, 2, 6]).unwrap();
+            let ptr = Malloced::as_ptr(&buf);
+
+            Malloced::sort_unstable(&mut buf);
+            assert_eq!(&*buf, &[-5, -1, 1, 2, 3, 4, 6, 9]);
+            assert_eq!(Malloced::as_ptr(&buf), ptr);
+        }
+
+        #[test]
+        fn binary_search() {
+            let mut buf = Malloced::<[i32]>::alloc(&[9, 1, 5, 3, 7]).unwrap();
+            Malloced::sort_unstable(&mut buf);
+
+            assert_eq!(Malloced::binary_search(&buf, &1), Ok(0));
+            assert_eq!(Malloced::binary_search(&buf, &7), Ok(3));
+            assert_eq!(Malloced::binary_search(&buf, &0), Err(0));
+            assert_eq!(Malloced::binary_search(&buf, &4), Err(2));
+            assert_eq!(Malloced::binary_search(&buf, &10), Err(5));
+        }
+
+        #[test]
+        fn binary_search_by() {
+            let buf = Malloced::<[i32]>::alloc(&[-3, 0, 2, 8, 13]).unwrap();
+
+            assert_eq!(Malloced::binary_search_by(&buf, |x| x.cmp(&8)), Ok(3));
+            assert_eq!(Malloced::binary_search_by(&buf, |x| x.cmp(&-3)), Ok(0));
+            assert_eq!(Malloced::binary_search_by(&buf, |x| x.cmp(&1)), Err(2));
+            assert_eq!(Malloced::binary_search_by(&buf, |x| x.cmp(&-5)), Err(0));
+            assert_eq!(Malloced::binary_search_by(&buf, |x| x.cmp(&20)), Err(5));
+        }
+
+        #[test]
+        fn binary_search_by_key() {
+            let buf = Malloced::<[(i32, char)]>::alloc(&[(1, 'a'), (4, 'b'), (9, 'c')]).unwrap();
+
+            assert_eq!(Malloced::binary_search_by_key(&buf, &4, |&(k, _)| k), Ok(1));
+            assert_eq!(
+                Malloced::binary_search_by_key(&buf, &5, |&(k, _)| k),
+                Err(2)
+            );
+            assert_eq!(
+                Malloced::binary_search_by_key(&buf, &0, |&(k, _)| k),
+                Err(0)
+            );
+            assert_eq!(
+                Malloced::binary_search_by_key(&buf, &10, |&(k, _)| k),
+                Err(3)
+            );
+        }
+
+        #[test]
+        fn partition_point() {
+            let buf = Malloced::<[i32]>::alloc(&[1, 2, 3, 5, 5, 8, 13]).unwrap();
+
+            assert_eq!(Malloced::partition_point(&buf, |&x| x < 5), 3);
+            assert_eq!(Malloced::partition_point(&buf, |&x| x <= 5), 5);
+            assert_eq!(Malloced::partition_point(&buf, |&x| x < 0), 0);
+            assert_eq!(Malloced::partition_point(&buf, |&x| x < 100), 7);
+
+            let empty = Malloced::<[i32]>::alloc(&[]).unwrap();
+            assert_eq!(Malloced::partition_point(&empty, |&x| x < 5), 0);
+        }
+    }
+
+    mod chunks {
+        use super::*;
+
+        #[test]
+        fn chunks() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4, 5]).unwrap();
+            let chunks: Vec<&[u8]> = Malloced::chunks(&buf, 2).collect();
+            assert_eq!(chunks, [&[1, 2][..], &[3, 4], &[5]]);
+        }
+
+        #[test]
+        fn chunks_exact() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4, 5]).unwrap();
+            let mut iter = Malloced::chunks_exact(&buf, 2);
+            assert_eq!(iter.next(), Some(&[1, 2][..]));
+            assert_eq!(iter.next(), Some(&[3, 4][..]));
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.remainder(), &[5]);
+        }
+
+        #[test]
+        fn windows() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4]).unwrap();
+            let windows: Vec<&[u8]> = Malloced::windows(&buf, 3).collect();
+            assert_eq!(windows, [&[1, 2, 3][..], &[2, 3, 4]]);
+
+            assert_eq!(Malloced::windows(&buf, 5).next(), None);
+        }
+
+        #[test]
+        fn empty() {
+            let buf = Malloced::<[u8]>::alloc(&[]).unwrap();
+            assert_eq!(Malloced::chunks(&buf, 2).next(), None);
+            assert_eq!(Malloced::chunks_exact(&buf, 2).next(), None);
+            assert_eq!(Malloced::windows(&buf, 2).next(), None);
+        }
+
+        #[test]
+        #[should_panic]
+        fn zero_size() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3]).unwrap();
+            let _ = Malloced::chunks(&buf, 0);
+        }
+    }
+
+    mod shrink_to {
+        use super::*;
+
+        #[test]
+        fn drops_tail() {
+            let drops = Cell::new(0);
+            let values: Vec<_> = (0..5).map(|i| DropCounter(&drops, i)).collect();
+            let mut buf = Malloced::<[DropCounter]>::alloc(&values).unwrap();
+            drop(values);
+            drops.set(0);
+
+            Malloced::shrink_to(&mut buf, 2);
+            assert_eq!(drops.get(), 3);
+            assert_eq!(buf.iter().map(|d| d.1).collect::<Vec<_>>(), [0, 1]);
+
+            // No-op when already short enough.
+            Malloced::shrink_to(&mut buf, 2);
+            Malloced::shrink_to(&mut buf, 10);
+            assert_eq!(drops.get(), 3);
+            assert_eq!(buf.len(), 2);
+
+            Malloced::shrink_to(&mut buf, 0);
+            assert_eq!(drops.get(), 5);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "ios",
+            windows,
+        ))]
+        fn usable_size() {
+            let mut buf = Malloced::<[u8]>::alloc(&[7; 4096]).unwrap();
+            let before = Malloced::usable_size(&buf);
+
+            Malloced::shrink_to(&mut buf, 16);
+            assert!(Malloced::usable_size(&buf) < before);
+            assert_eq!(&*buf, &[7; 16]);
+        }
+
+        // `realloc` cannot preserve alignments above what `malloc` guarantees,
+        // so shrinking keeps the allocation instead of panicking.
+        #[test]
+        #[cfg(unix)]
+        fn over_aligned() {
+            use core::{
+                ffi::{c_int, c_void},
+                mem, ptr,
+            };
+
+            extern "C" {
+                fn posix_memalign(ptr: *mut *mut c_void, align: usize, size: usize) -> c_int;
+            }
+
+            #[derive(Clone, Copy, Debug, PartialEq)]
+            #[repr(align(64))]
+            struct A64(u32);
+
+            fn alloc(values: &[u32]) -> Malloced<[A64]> {
+                let mut buf = ptr::null_mut();
+                let size = values.len() * mem::size_of::<A64>();
+                assert_eq!(unsafe { posix_memalign(&mut buf, 64, size) }, 0);
+
+                let buf = buf as *mut A64;
+                for (i, &value) in values.iter().enumerate() {
+                    unsafe { buf.add(i).write(A64(value)) };
+                }
+                unsafe { Malloced::slice_from_raw_parts(buf, values.len()) }
+            }
+
+            let mut buf = alloc(&[1, 2, 3, 4, 5, 6]);
+            Malloced::retain(&mut buf, |a| a.0 % 2 == 0);
+            assert_eq!(&*buf, &[A64(2), A64(4), A64(6)]);
+
+            let mut buf = alloc(&[1, 1, 2, 2, 2, 3]);
+            Malloced::dedup(&mut buf);
+            assert_eq!(&*buf, &[A64(1), A64(2), A64(3)]);
+
+            let mut buf = alloc(&[1, 2, 3, 4]);
+            Malloced::shrink_to(&mut buf, 1);
+            assert_eq!(&*buf, &[A64(1)]);
+            assert_eq!(Malloced::as_ptr(&buf) as *const A64 as usize % 64, 0);
+
+            let mut buf = alloc(&[1, 2, 3, 4]);
+            let odd: Vec<A64> = Malloced::extract_if(&mut buf, |a| a.0 % 2 == 1).collect();
+            assert_eq!(odd, [A64(1), A64(3)]);
+            assert_eq!(&*buf, &[A64(2), A64(4)]);
+        }
+    }
+
+    mod set_len {
+        use super::*;
+
+        #[test]
+        fn partial_fill() {
+            let (ptr, len) = Malloced::into_raw_parts(Malloced::<[u16]>::new_uninit_slice(8));
+            assert_eq!(len, 8);
+
+            let ptr = ptr as *mut u16;
+            let mut buf = unsafe { Malloced::slice_from_raw_parts(ptr, 0) };
+            assert!(buf.is_empty());
+
+            unsafe {
+                for i in 0..3 {
+                    ptr.add(i).write(i as u16 * 10);
+                }
+                Malloced::set_len(&mut buf, 3);
+            }
+            assert_eq!(&*buf, &[0, 10, 20]);
+            assert_eq!(Malloced::as_ptr(&buf) as *const u16, ptr as *const u16);
+
+            unsafe { Malloced::set_len(&mut buf, 1) };
+            assert_eq!(&*buf, &[0]);
+        }
+    }
+
+    mod as_chunks {
+        use super::*;
+
+        #[test]
+        fn divisible() {
+            let mut buf = Malloced::<[u16]>::alloc(&[1, 2, 3, 4, 5, 6]).unwrap();
+
+            let (chunks, remainder) = Malloced::as_chunks::<3>(&buf);
+            assert_eq!(chunks, &[[1, 2, 3], [4, 5, 6]]);
+            assert!(remainder.is_empty());
+
+            let (chunks, remainder) = Malloced::as_chunks_mut::<2>(&mut buf);
+            assert!(remainder.is_empty());
+            for chunk in chunks {
+                chunk.swap(0, 1);
+            }
+            assert_eq!(&*buf, &[2, 1, 4, 3, 6, 5]);
+        }
+
+        #[test]
+        fn remainder() {
+            let mut buf = Malloced::<[u32]>::alloc(&[1, 2, 3, 4, 5]).unwrap();
+
+            let (chunks, remainder) = Malloced::as_chunks::<2>(&buf);
+            assert_eq!(chunks, &[[1, 2], [3, 4]]);
+            assert_eq!(remainder, &[5]);
+
+            let (chunks, remainder) = Malloced::as_chunks::<8>(&buf);
+            assert!(chunks.is_empty());
+            assert_eq!(remainder, &[1, 2, 3, 4, 5]);
+
+            let (chunks, remainder) = Malloced::as_chunks_mut::<4>(&mut buf);
+            chunks[0][0] = 10;
+            remainder[0] = 50;
+            assert_eq!(&*buf, &[10, 2, 3, 4, 50]);
+        }
+
+        #[test]
+        #[should_panic = "chunk size must be non-zero"]
+        fn zero() {
+            let buf = Malloced::<[u8]>::alloc(&[1]).unwrap();
+            let _ = Malloced::as_chunks::<0>(&buf);
+        }
+    }
+
+    mod chunk_by {
+        use super::*;
+
+        #[test]
+        fn runs() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 1, 2, 2, 2, 3]).unwrap();
+
+            let runs: Vec<&[u8]> = Malloced::chunk_by(&buf, |a, b| a == b).collect();
+            assert_eq!(runs, [&[1, 1][..], &[2, 2, 2], &[3]]);
+
+            let runs: Vec<&[u8]> = Malloced::chunk_by(&buf, |a, b| a == b).rev().collect();
+            assert_eq!(runs, [&[3][..], &[2, 2, 2], &[1, 1]]);
+        }
+
+        #[test]
+        fn sorted() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 1, 5, 0]).unwrap();
+
+            let mut runs = Malloced::chunk_by(&buf, |a, b| a <= b);
+            assert_eq!(runs.next(), Some(&[1, 2, 3][..]));
+            assert_eq!(runs.next_back(), Some(&[0][..]));
+            assert_eq!(runs.next(), Some(&[1, 5][..]));
+            assert_eq!(runs.next(), None);
+            assert_eq!(runs.next_back(), None);
+        }
+
+        #[test]
+        fn empty() {
+            let buf = Malloced::<[u8]>::alloc(&[]).unwrap();
+            assert_eq!(Malloced::chunk_by(&buf, |_, _| true).next(), None);
+        }
+    }
+
+    mod retain {
+        use super::*;
+
+        #[test]
+        fn even() {
+            let mut buf = Malloced::<[u32]>::alloc(&[1, 2, 3, 4, 5, 6]).unwrap();
+            Malloced::retain(&mut buf, |&x| x % 2 == 0);
+            assert_eq!(&*buf, &[2, 4, 6]);
+
+            Malloced::retain(&mut buf, |_| true);
+            assert_eq!(&*buf, &[2, 4, 6]);
+
+            Malloced::retain(&mut buf, |_| false);
+            assert_eq!(&*buf, &[]);
+        }
+
+        #[test]
+        fn zst() {
+            let mut buf = Malloced::<[()]>::alloc(&[(), (), ()]).unwrap();
+            let mut keep = false;
+            Malloced::retain(&mut buf, |_| {
+                keep = !keep;
+                keep
+            });
+            assert_eq!(buf.len(), 2);
+        }
+
+        #[test]
+        fn drops() {
+            let drops = Cell::new(0);
+            let values: Vec<_> = (0..6).map(|i| DropCounter(&drops, i)).collect();
+            let mut buf = Malloced::<[DropCounter]>::alloc(&values).unwrap();
+            drop(values);
+            drops.set(0);
+
+            Malloced::retain(&mut buf, |x| x.1 % 3 == 0);
+            assert_eq!(drops.get(), 4);
+            assert_eq!(buf.iter().map(|x| x.1).collect::<Vec<_>>(), [0, 3]);
+
+            drop(buf);
+            assert_eq!(drops.get(), 6);
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn panic() {
+            let drops = Cell::new(0);
+            let values: Vec<_> = (0..6).map(|i| DropCounter(&drops, i)).collect();
+            let mut buf = Malloced::<[DropCounter]>::alloc(&values).unwrap();
+            drop(values);
+            drops.set(0);
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                Malloced::retain(&mut buf, |x| {
+                    if x.1 == 3 {
+                        panic!("retain panic");
+                    }
+                    x.1 % 2 == 0
+                });
+            }));
+            assert!(result.is_err());
+
+            // Element 1 was removed; 3, 4, and 5 were not yet visited.
+            assert_eq!(drops.get(), 1);
+            assert_eq!(buf.iter().map(|x| x.1).collect::<Vec<_>>(), [0, 2, 3, 4, 5]);
+
+            drop(buf);
+            assert_eq!(drops.get(), 6);
+        }
+    }
+
+    mod split_at {
+        use super::*;
+
+        #[test]
+        fn middle() {
+            let buf = Malloced::<[u32]>::alloc(&[1, 2, 3, 4, 5]).unwrap();
+            let (left, right) = Malloced::split_at(buf, 2);
+            assert_eq!(&*left, &[1, 2]);
+            assert_eq!(&*right, &[3, 4, 5]);
+
+            drop(left);
+            assert_eq!(&*right, &[3, 4, 5]);
+        }
+
+        #[test]
+        fn boundaries() {
+            let buf = Malloced::<[u32]>::alloc(&[1, 2, 3]).unwrap();
+            let (left, right) = Malloced::split_at(buf, 0);
+            assert_eq!(&*left, &[]);
+            assert_eq!(&*right, &[1, 2, 3]);
+
+            let (left, right) = Malloced::split_at(right, 3);
+            assert_eq!(&*left, &[1, 2, 3]);
+            assert_eq!(&*right, &[]);
+
+            drop(left);
+            assert_eq!(&*right, &[]);
+
+            let (left, right) = Malloced::split_at(right, 0);
+            assert!(left.is_empty() && right.is_empty());
+        }
+
+        #[test]
+        fn zst() {
+            let buf = Malloced::<[()]>::alloc(&[(), (), ()]).unwrap();
+            let (left, right) = Malloced::split_at(buf, 1);
+            assert_eq!(left.len(), 1);
+            assert_eq!(right.len(), 2);
+        }
+
+        #[test]
+        #[should_panic = "split index (is 4) should be <= len (is 3)"]
+        fn out_of_bounds() {
+            let buf = Malloced::<[u32]>::alloc(&[1, 2, 3]).unwrap();
+            let _ = Malloced::split_at(buf, 4);
+        }
+    }
+
+    mod append {
+        use super::*;
+
+        #[test]
+        fn append() {
+            let mut buf = Malloced::<[u32]>::alloc(&[1, 2, 3]).unwrap();
+            Malloced::append(&mut buf, Malloced::alloc(&[4, 5]).unwrap());
+            assert_eq!(&*buf, &[1, 2, 3, 4, 5]);
+
+            // Grow enough that `realloc` is likely to move the buffer.
+            let tail: Vec<u32> = (6..=1000).collect();
+            Malloced::append(&mut buf, Malloced::alloc(&tail).unwrap());
+            assert_eq!(buf.len(), 1000);
+            assert!(buf.iter().copied().eq(1..=1000));
+        }
+
+        #[test]
+        fn empty() {
+            let mut buf = Malloced::<[u8]>::alloc(&[]).unwrap();
+            Malloced::append(&mut buf, Malloced::alloc(&[]).unwrap());
+            assert!(buf.is_empty());
+
+            Malloced::append(&mut buf, Malloced::alloc(&[1, 2]).unwrap());
+            assert_eq!(&*buf, &[1, 2]);
+
+            Malloced::append(&mut buf, Malloced::alloc(&[]).unwrap());
+            assert_eq!(&*buf, &[1, 2]);
+        }
+
+        #[test]
+        fn zst() {
+            let mut buf = Malloced::<[()]>::alloc(&[(); 2]).unwrap();
+            Malloced::append(&mut buf, Malloced::alloc(&[(); 3]).unwrap());
+            assert_eq!(buf.len(), 5);
+        }
+    }
+
+    mod from_slices {
+        use super::*;
+
+        #[test]
+        fn bytes() {
+            let buf = Malloced::<[u8]>::from_slices(&[b"ab", b"", b"cde"]);
+            assert_eq!(&*buf, b"abcde");
+
+            let buf = Malloced::<[u8]>::from_slices(&[b"x", b"yz", b"!"]);
+            assert_eq!(&*buf, b"xyz!");
+        }
+
+        #[test]
+        fn empty() {
+            assert!(Malloced::<[u32]>::from_slices(&[]).is_empty());
+            assert!(Malloced::<[u32]>::from_slices(&[&[], &[]]).is_empty());
+        }
+
+        #[test]
+        fn zst() {
+            let buf = Malloced::<[()]>::from_slices(&[&[(); 2], &[(); 3]]);
+            assert_eq!(buf.len(), 5);
+        }
+
+        #[test]
+        #[should_panic(expected = "capacity overflow")]
+        fn overflow() {
+            let huge = [(); usize::MAX];
+            Malloced::<[()]>::from_slices(&[&huge, &[()]]);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod boxed_slice {
+        use super::*;
+        use std::{boxed::Box, string::String};
+
+        #[test]
+        fn round_trip() {
+            let boxed: Box<[String]> = Box::new([String::from("a"), String::from("bc")]);
+            let buf = Malloced::from_boxed_slice(boxed);
+            assert_eq!(&*buf, &["a", "bc"]);
+
+            let boxed = Malloced::to_boxed_slice(&buf);
+            assert_eq!(&*boxed, &["a", "bc"]);
+            assert_eq!(&*buf, &["a", "bc"]);
+
+            let buf = Malloced::from_boxed_slice(Box::<[String]>::from([]));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn drops() {
+            let drops = Cell::new(0);
+            let boxed: Box<[DropCounter]> = (0..3).map(|i| DropCounter(&drops, i)).collect();
+
+            // Moving into `malloc`-ed memory must not drop anything.
+            let buf = Malloced::from_boxed_slice(boxed);
+            assert_eq!(drops.get(), 0);
+
+            let boxed = Malloced::to_boxed_slice(&buf);
+            assert_eq!(drops.get(), 0);
+
+            drop(buf);
+            assert_eq!(drops.get(), 3);
+            assert_eq!(boxed.iter().map(|d| d.1).collect::<Vec<_>>(), [0, 1, 2]);
+
+            drop(boxed);
+            assert_eq!(drops.get(), 6);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    mod into_vec_copied {
+        use super::*;
+
+        #[test]
+        fn matches_into_iter() {
+            let values: Vec<u64> = (0..10_000).map(|i| i * 31 % 977).collect();
+
+            let fast = Malloced::into_vec_copied(Malloced::<[u64]>::alloc(&values).unwrap());
+            let slow: Vec<u64> = Malloced::<[u64]>::alloc(&values)
+                .unwrap()
+                .into_iter()
+                .collect();
+
+            assert_eq!(fast, slow);
+            assert_eq!(fast, values);
+        }
+
+        #[test]
+        fn empty_and_zst() {
+            let empty = Malloced::into_vec_copied(Malloced::<[u8]>::alloc(&[]).unwrap());
+            assert!(empty.is_empty());
+
+            let units = Malloced::into_vec_copied(Malloced::<[()]>::alloc(&[(); 3]).unwrap());
+            assert_eq!(units.len(), 3);
+        }
+    }
+
+    mod try_cast_slice {
+        use super::*;
+
+        #[test]
+        fn success() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 0, 0, 0, 2, 0, 0, 0]).unwrap();
+            let ptr = Malloced::as_ptr(&buf) as *const u8;
+
+            let words = unsafe { Malloced::try_cast_slice::<u32>(buf) }
+                .ok()
+                .unwrap();
+            assert_eq!(Malloced::as_ptr(&words) as *const u8, ptr);
+            assert_eq!(
+                &*words,
+                &[
+                    u32::from_le_bytes([1, 0, 0, 0]),
+                    u32::from_le_bytes([2, 0, 0, 0])
+                ]
+            );
+
+            let bytes = unsafe { Malloced::try_cast_slice::<u8>(words) }
+                .ok()
+                .unwrap();
+            assert_eq!(bytes.len(), 8);
+        }
+
+        #[test]
+        fn bad_len() {
+            let buf = Malloced::<[u8]>::alloc(&[1, 2, 3, 4, 5, 6]).unwrap();
+            let buf = unsafe { Malloced::try_cast_slice::<u32>(buf) }.unwrap_err();
+            assert_eq!(&*buf, &[1, 2, 3, 4, 5, 6]);
+
+            let buf = unsafe { Malloced::try_cast_slice::<()>(buf) }.unwrap_err();
+            assert_eq!(buf.len(), 6);
+        }
+
+        #[test]
+        fn bad_align() {
+            #[derive(Debug)]
+            #[repr(align(4096))]
+            struct Page(#[allow(dead_code)] [u8; 4096]);
+
+            // `malloc` only guarantees a small alignment, so look for a buffer
+            // that is not page-aligned while keeping the others alive.
+            let mut bufs = Vec::new();
+            let buf = loop {
+                let buf = Malloced::<[u8]>::alloc(&[0; 4096]).unwrap();
+                if Malloced::as_ptr(&buf) as *const u8 as usize % 4096 != 0 {
+                    break buf;
+                }
+                bufs.push(buf);
+            };
+
+            let buf = unsafe { Malloced::try_cast_slice::<Page>(buf) }.unwrap_err();
+            assert_eq!(buf.len(), 4096);
+        }
+    }
+
+    mod extract_if {
+        use super::*;
+
+        #[test]
+        fn odd() {
+            let mut buf = Malloced::<[u32]>::alloc(&[1, 2, 3, 4, 5, 6, 7]).unwrap();
+
+            let odd: Vec<u32> = Malloced::extract_if(&mut buf, |x| *x % 2 == 1).collect();
+            assert_eq!(odd, [1, 3, 5, 7]);
+            assert_eq!(&*buf, &[2, 4, 6]);
+
+            let none: Vec<u32> = Malloced::extract_if(&mut buf, |_| false).collect();
+            assert!(none.is_empty());
+            assert_eq!(&*buf, &[2, 4, 6]);
+
+            let all: Vec<u32> = Malloced::extract_if(&mut buf, |_| true).collect();
+            assert_eq!(all, [2, 4, 6]);
+            assert_eq!(&*buf, &[]);
+        }
+
+        #[test]
+        fn mutate() {
+            let mut buf = Malloced::<[u32]>::alloc(&[1, 2, 3, 4]).unwrap();
+
+            let extracted: Vec<u32> = Malloced::extract_if(&mut buf, |x| {
+                *x *= 10;
+                *x > 20
+            })
+            .collect();
+            assert_eq!(extracted, [30, 40]);
+            assert_eq!(&*buf, &[10, 20]);
+        }
+
+        #[test]
+        fn partial() {
+            let mut buf = Malloced::<[u32]>::alloc(&[1, 2, 3, 4, 5, 6]).unwrap();
+
+            let mut iter = Malloced::extract_if(&mut buf, |x| *x % 2 == 1);
+            assert_eq!(iter.next(), Some(1));
+            drop(iter);
+
+            // Only 1 was visited and extracted; 2 onward were not visited.
+            assert_eq!(&*buf, &[2, 3, 4, 5, 6]);
+        }
+
+        #[test]
+        fn zst() {
+            let mut buf = Malloced::<[()]>::alloc(&[(), (), ()]).unwrap();
+            let mut extract = false;
+            let count = Malloced::extract_if(&mut buf, |_| {
+                extract = !extract;
+                extract
+            })
+            .count();
+            assert_eq!(count, 2);
+            assert_eq!(buf.len(), 1);
+        }
+
+        #[test]
+        fn drops() {
+            let drops = Cell::new(0);
+            let values: Vec<_> = (0..6).map(|i| DropCounter(&drops, i)).collect();
+            let mut buf = Malloced::<[DropCounter]>::alloc(&values).unwrap();
+            drop(values);
+            drops.set(0);
+
+            let extracted: Vec<_> = Malloced::extract_if(&mut buf, |x| x.1 < 2).collect();
+            assert_eq!(drops.get(), 0);
+            assert_eq!(buf.iter().map(|x| x.1).collect::<Vec<_>>(), [2, 3, 4, 5]);
+
+            drop(extracted);
+            assert_eq!(drops.get(), 2);
+
+            drop(buf);
+            assert_eq!(drops.get(), 6);
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn panic() {
+            let drops = Cell::new(0);
+            let values: Vec<_> = (0..6).map(|i| DropCounter(&drops, i)).collect();
+            let mut buf = Malloced::<[DropCounter]>::alloc(&values).unwrap();
+            drop(values);
+            drops.set(0);
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                Malloced::extract_if(&mut buf, |x| {
+                    if x.1 == 3 {
+                        panic!("extract_if panic");
+                    }
+                    x.1 % 2 == 1
+                })
+                .for_each(drop);
+            }));
+            assert!(result.is_err());
+
+            // Element 1 was extracted; 3, 4, and 5 were not yet visited.
+            assert_eq!(drops.get(), 1);
+            assert_eq!(buf.iter().map(|x| x.1).collect::<Vec<_>>(), [0, 2, 3, 4, 5]);
+
+            drop(buf);
+            assert_eq!(drops.get(), 6);
+        }
+    }
+
+    mod dedup {
+        use super::*;
+
+        #[test]
+        fn dedup() {
+            let mut buf = Malloced::<[u32]>::alloc(&[1, 1, 2, 3, 3, 3]).unwrap();
+            Malloced::dedup(&mut buf);
+            assert_eq!(&*buf, &[1, 2, 3]);
+
+            let mut empty = Malloced::<[u32]>::alloc(&[]).unwrap();
+            Malloced::dedup(&mut empty);
+            assert_eq!(&*empty, &[]);
+
+            let mut zst = Malloced::<[()]>::alloc(&[(), (), ()]).unwrap();
+            Malloced::dedup(&mut zst);
+            assert_eq!(zst.len(), 1);
+        }
+
+        #[test]
+        fn dedup_by_key() {
+            let mut buf = Malloced::<[i32]>::alloc(&[10, 11, 20, 21, 22, 30]).unwrap();
+            Malloced::dedup_by_key(&mut buf, |x| *x / 10);
+            assert_eq!(&*buf, &[10, 20, 30]);
+        }
+
+        #[test]
+        fn dedup_by() {
+            let mut buf = Malloced::<[i32]>::alloc(&[1, 2, 4, 5, 7]).unwrap();
+            Malloced::dedup_by(&mut buf, |a, b| *a - *b == 1);
+            assert_eq!(&*buf, &[1, 4, 7]);
+        }
+
+        #[test]
+        fn drops() {
+            let drops = Cell::new(0);
+            let values: Vec<_> = [1, 1, 2, 2, 2, 3]
+                .iter()
+                .map(|&i| DropCounter(&drops, i))
+                .collect();
+            let mut buf = Malloced::<[DropCounter]>::alloc(&values).unwrap();
+            drop(values);
+            drops.set(0);
+
+            Malloced::dedup_by_key(&mut buf, |x| x.1);
+            assert_eq!(drops.get(), 3);
+            assert_eq!(buf.iter().map(|x| x.1).collect::<Vec<_>>(), [1, 2, 3]);
+
+            drop(buf);
+            assert_eq!(drops.get(), 6);
+        }
+    }
+}
@@ -1,8 +1,120 @@
-use core::ffi::c_void;
+use core::ffi::{c_int, c_void};
 
+#[cfg(not(all(test, feature = "std")))]
 extern "C" {
-    #[cfg(test)]
     pub fn malloc(len: usize) -> *mut u8;
 
+    pub fn realloc(ptr: *mut c_void, len: usize) -> *mut u8;
+}
+
+#[cfg(all(test, feature = "std"))]
+pub use self::counting::{fail_next_alloc, malloc, malloc_count, realloc};
+
+extern "C" {
     pub fn free(ptr: *mut c_void);
+
+    pub fn memcmp(a: *const c_void, b: *const c_void, len: usize) -> c_int;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn malloc_usable_size(ptr: *mut c_void) -> usize;
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    fn malloc_size(ptr: *const c_void) -> usize;
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    fn malloc_good_size(size: usize) -> usize;
+
+    #[cfg(windows)]
+    fn _msize(ptr: *mut c_void) -> usize;
+}
+
+/// Returns the number of bytes usable in the allocation at `ptr`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[inline]
+pub unsafe fn usable_size(ptr: *mut c_void) -> usize {
+    malloc_usable_size(ptr)
+}
+
+/// Returns the number of bytes usable in the allocation at `ptr`.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+#[inline]
+pub unsafe fn usable_size(ptr: *mut c_void) -> usize {
+    malloc_size(ptr)
+}
+
+/// Returns the number of bytes usable in the allocation at `ptr`.
+#[cfg(windows)]
+#[inline]
+pub unsafe fn usable_size(ptr: *mut c_void) -> usize {
+    _msize(ptr)
+}
+
+/// Returns the size that `malloc` would actually allocate for `size` bytes.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+#[inline]
+pub fn good_size(size: usize) -> usize {
+    unsafe { malloc_good_size(size) }
+}
+
+/// Returns the size that `malloc` would actually allocate for `size` bytes.
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+#[inline]
+pub fn good_size(size: usize) -> usize {
+    size
+}
+
+/// Wrappers that count calls to `malloc` and `realloc` on the current thread
+/// so that tests can assert whether an operation allocates, and that can be
+/// made to fail so that tests can exercise allocation errors.
+#[cfg(all(test, feature = "std"))]
+mod counting {
+    use core::{cell::Cell, ffi::c_void};
+
+    std::thread_local! {
+        static COUNT: Cell<usize> = const { Cell::new(0) };
+        static FAIL_NEXT: Cell<bool> = const { Cell::new(false) };
+    }
+
+    mod ffi {
+        use core::ffi::c_void;
+
+        extern "C" {
+            pub fn malloc(len: usize) -> *mut u8;
+
+            pub fn realloc(ptr: *mut c_void, len: usize) -> *mut u8;
+        }
+    }
+
+    /// Counts a call and returns whether it should fail.
+    #[inline]
+    fn increment() -> bool {
+        COUNT.with(|count| count.set(count.get() + 1));
+        FAIL_NEXT.with(|fail| fail.replace(false))
+    }
+
+    pub unsafe fn malloc(len: usize) -> *mut u8 {
+        if increment() {
+            return core::ptr::null_mut();
+        }
+        ffi::malloc(len)
+    }
+
+    pub unsafe fn realloc(ptr: *mut c_void, len: usize) -> *mut u8 {
+        if increment() {
+            return core::ptr::null_mut();
+        }
+        ffi::realloc(ptr, len)
+    }
+
+    /// Makes the next `malloc` or `realloc` call on the current thread return
+    /// null, as if the allocator were out of memory.
+    pub fn fail_next_alloc() {
+        FAIL_NEXT.with(|fail| fail.set(true));
+    }
+
+    /// Returns the number of `malloc` and `realloc` calls made so far on the
+    /// current thread.
+    pub fn malloc_count() -> usize {
+        COUNT.with(|count| count.get())
+    }
 }
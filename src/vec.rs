@@ -0,0 +1,1136 @@
+use crate::{
+    capacity_overflow, malloc_array, realloc_array, sys, try_malloc_array, try_realloc_array,
+    AllocError, Malloced,
+};
+use core::{
+    ffi::c_void,
+    fmt,
+    iter::{FromIterator, FusedIterator},
+    marker::PhantomData,
+    mem,
+    ops::{Bound, Deref, DerefMut, RangeBounds},
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// A growable array backed by `malloc`-ed memory.
+///
+/// This is useful for building a
+/// <code>[Malloced](crate::Malloced)<[\[T\]](prim@slice)></code> whose length
+/// is not known upfront. Memory grows via `realloc`, and
+/// [`into_boxed_slice`](Self::into_boxed_slice) shrinks the allocation to the
+/// exact length.
+///
+/// Types aligned to more than `malloc` guarantees are not supported. `realloc`
+/// cannot preserve a larger alignment, so resizing the buffer of such a type
+/// panics.
+pub struct MallocedVec<T> {
+    buf: NonNull<T>,
+    cap: usize,
+    len: usize,
+
+    // Marks ownership of instances of T.
+    marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for MallocedVec<T> {}
+unsafe impl<T: Sync> Sync for MallocedVec<T> {}
+
+impl<T> MallocedVec<T> {
+    /// Creates an empty vector without allocating.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            buf: NonNull::dangling(),
+            cap: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+            len: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Creates an empty vector with space for at least `capacity` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation size overflows, if `malloc` fails, or if
+    /// `malloc` does not return memory aligned for `T`.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut vec = Self::new();
+        if capacity > vec.cap {
+            vec.buf = malloc_array(capacity);
+            vec.cap = capacity;
+        }
+        vec
+    }
+
+    /// Returns the number of elements in the vector.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of elements the vector can hold without
+    /// reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Extracts a slice containing the entire vector.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.buf.as_ptr(), self.len) }
+    }
+
+    /// Extracts a mutable slice containing the entire vector.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.buf.as_ptr(), self.len) }
+    }
+
+    /// Appends an element to the back of the vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation size overflows, if `realloc` fails, or if `T`
+    /// is aligned to more than `malloc` guarantees.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.grow_amortized(1);
+        }
+
+        unsafe { self.buf.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    /// Removes the last element from the vector and returns it, or `None` if
+    /// it is empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { self.buf.as_ptr().add(self.len).read() })
+        }
+    }
+
+    /// Inserts an element at `index`, shifting all elements after it to the
+    /// right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`, if the allocation size overflows, if `realloc`
+    /// fails, or if `T` is aligned to more than `malloc` guarantees.
+    #[inline]
+    #[track_caller]
+    pub fn insert(&mut self, index: usize, value: T) {
+        let len = self.len;
+        if index > len {
+            insert_index_fail(index, len);
+        }
+
+        if len == self.cap {
+            self.grow_amortized(1);
+        }
+
+        unsafe {
+            let ptr = self.buf.as_ptr().add(index);
+            ptr::copy(ptr, ptr.add(1), len - index);
+            ptr.write(value);
+        }
+        self.len = len + 1;
+    }
+
+    /// Removes and returns the element at `index`, replacing it with the last
+    /// element.
+    ///
+    /// This does not preserve ordering, but is O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    #[track_caller]
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        if index >= len {
+            swap_remove_index_fail(index, len);
+        }
+
+        unsafe {
+            let base = self.buf.as_ptr();
+            let value = base.add(index).read();
+            ptr::copy(base.add(len - 1), base.add(index), 1);
+            self.len = len - 1;
+            value
+        }
+    }
+
+    /// Removes and returns the element at `index`, shifting all elements after
+    /// it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    #[track_caller]
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        if index >= len {
+            remove_index_fail(index, len);
+        }
+
+        unsafe {
+            let ptr = self.buf.as_ptr().add(index);
+            let value = ptr.read();
+            ptr::copy(ptr.add(1), ptr, len - index - 1);
+            self.len = len - 1;
+            value
+        }
+    }
+
+    /// Removes the elements in `range` from the vector, returning them as an
+    /// iterator.
+    ///
+    /// When the iterator is dropped, any remaining elements in the range are
+    /// dropped and the elements after the range are shifted back. If the
+    /// iterator is leaked (e.g. via [`mem::forget`]), the vector may lose the
+    /// elements in and after the range, but remains safe to use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end or if the end
+    /// is greater than the length of the vector.
+    #[track_caller]
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, T>
+    where
+        R: RangeBounds<usize>,
+    {
+        let len = self.len;
+
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).unwrap_or_else(|| capacity_overflow()),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).unwrap_or_else(|| capacity_overflow()),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+
+        if start > end {
+            drain_order_fail(start, end);
+        }
+        if end > len {
+            drain_end_fail(end, len);
+        }
+
+        // Truncate up front so that leaking the `Drain` cannot expose moved
+        // or dropped elements.
+        self.len = start;
+
+        let range = unsafe { slice::from_raw_parts(self.buf.as_ptr().add(start), end - start) };
+
+        Drain {
+            vec: NonNull::from(self),
+            iter: range.iter(),
+            tail_start: end,
+            tail_len: len - end,
+        }
+    }
+
+    /// Converts the vector into a
+    /// <code>[Malloced](crate::Malloced)<[\[T\]](prim@slice)></code>.
+    ///
+    /// The allocation is shrunk to the exact length, so the result has no
+    /// unused capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `malloc` or `realloc` fails, or if `T` is aligned to more than
+    /// `malloc` guarantees.
+    pub fn into_boxed_slice(mut self) -> Malloced<[T]> {
+        let len = self.len;
+
+        if len == 0 || mem::size_of::<T>() == 0 {
+            // `Malloced<[T]>` always owns a `malloc`-ed pointer to `free`, so
+            // one is needed even when there are no bytes to store. An existing
+            // buffer is reused, since `realloc_array` never requests 0 bytes.
+            let buf = if self.is_allocated() {
+                let buf = realloc_array(self.buf, 0);
+                self.buf = NonNull::dangling();
+                self.cap = 0;
+                buf
+            } else {
+                malloc_array::<T>(0)
+            };
+
+            // Ownership of any ZST elements is transferred to the result.
+            self.len = 0;
+
+            return unsafe { Malloced::slice_from_raw_parts(buf.as_ptr(), len) };
+        }
+
+        if self.cap != len {
+            self.set_capacity(len);
+        }
+
+        let this = mem::ManuallyDrop::new(self);
+        unsafe { Malloced::slice_from_raw_parts(this.buf.as_ptr(), len) }
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    ///
+    /// Capacity grows geometrically to avoid repeated calls to `realloc`. Does
+    /// nothing if the capacity is already sufficient.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation size overflows, if `realloc` fails, or if `T`
+    /// is aligned to more than `malloc` guarantees.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        if self.cap - self.len < additional {
+            self.grow_amortized(additional);
+        }
+    }
+
+    /// Tries to reserve capacity for at least `additional` more elements.
+    ///
+    /// This grows capacity like [`reserve`](Self::reserve), but returns an
+    /// error instead of panicking if the allocation size overflows, if
+    /// `malloc`/`realloc` fails, or if `T` is aligned to more than `malloc`
+    /// guarantees. On error, the vector is left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AllocError`] if the memory could not be allocated.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), AllocError> {
+        if self.cap - self.len >= additional {
+            return Ok(());
+        }
+
+        let required = self.len.checked_add(additional).ok_or(AllocError)?;
+        let cap = required.max(self.cap.saturating_mul(2)).max(4);
+
+        // Zero-sized types never reach this point since their capacity is
+        // `usize::MAX`.
+        self.buf = if self.is_allocated() {
+            try_realloc_array(self.buf, cap)?
+        } else {
+            try_malloc_array(cap)?
+        };
+        self.cap = cap;
+        Ok(())
+    }
+
+    /// Reserves capacity for exactly `additional` more elements, if the
+    /// capacity is not already sufficient.
+    ///
+    /// Prefer [`reserve`](Self::reserve) if future insertions are expected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation size overflows, if `realloc` fails, or if `T`
+    /// is aligned to more than `malloc` guarantees.
+    #[inline]
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if self.cap - self.len < additional {
+            match self.len.checked_add(additional) {
+                Some(cap) => self.set_capacity(cap),
+                None => capacity_overflow(),
+            }
+        }
+    }
+
+    /// Appends the elements of `iter`, reserving space based on its lower
+    /// size bound.
+    pub(crate) fn extend_desugared<I: Iterator<Item = T>>(&mut self, iter: I) {
+        self.reserve(iter.size_hint().0);
+
+        // `push` keeps `len` in sync with the initialized elements, so if the
+        // iterator panics, the vector remains valid.
+        for value in iter {
+            self.push(value);
+        }
+    }
+
+    /// Appends the elements of an iterator whose exact length is `additional`,
+    /// without checking capacity for each element.
+    ///
+    /// # Safety
+    ///
+    /// `iter` must yield no more than `additional` elements.
+    #[cfg(feature = "nightly")]
+    pub(crate) unsafe fn extend_trusted<I: Iterator<Item = T>>(
+        &mut self,
+        iter: I,
+        additional: usize,
+    ) {
+        self.reserve_exact(additional);
+
+        // Keeping `len` in sync with the initialized elements means that if
+        // the iterator panics, the vector remains valid.
+        for value in iter {
+            self.buf.as_ptr().add(self.len).write(value);
+            self.len += 1;
+        }
+    }
+
+    /// Shrinks the capacity to match the length, via `realloc`.
+    ///
+    /// If the vector is empty, its memory is freed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `realloc` fails or if `T` is aligned to more than `malloc`
+    /// guarantees.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        if mem::size_of::<T>() != 0 && self.cap != self.len {
+            self.set_capacity(self.len);
+        }
+    }
+
+    /// Returns `true` if `buf` was allocated with `malloc`.
+    #[inline]
+    fn is_allocated(&self) -> bool {
+        mem::size_of::<T>() != 0 && self.cap != 0
+    }
+
+    /// Grows the capacity to hold at least `additional` more elements,
+    /// doubling it to amortize the cost of `realloc`.
+    #[cold]
+    fn grow_amortized(&mut self, additional: usize) {
+        let required = match self.len.checked_add(additional) {
+            Some(required) => required,
+            None => capacity_overflow(),
+        };
+
+        let cap = required.max(self.cap.saturating_mul(2)).max(4);
+        self.set_capacity(cap);
+    }
+
+    /// Reallocates the buffer to hold exactly `cap` elements, freeing it if
+    /// `cap` is 0.
+    ///
+    /// `cap` must be at least `len`.
+    fn set_capacity(&mut self, cap: usize) {
+        debug_assert!(cap >= self.len);
+
+        if mem::size_of::<T>() == 0 {
+            return;
+        }
+
+        if cap == 0 {
+            if self.is_allocated() {
+                unsafe { sys::free(self.buf.as_ptr() as *mut c_void) };
+            }
+            self.buf = NonNull::dangling();
+        } else if self.is_allocated() {
+            self.buf = realloc_array(self.buf, cap);
+        } else {
+            self.buf = malloc_array(cap);
+        }
+        self.cap = cap;
+    }
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn insert_index_fail(index: usize, len: usize) -> ! {
+    panic!(
+        "insertion index (is {}) should be <= len (is {})",
+        index, len
+    )
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn swap_remove_index_fail(index: usize, len: usize) -> ! {
+    panic!(
+        "swap_remove index (is {}) should be < len (is {})",
+        index, len
+    )
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn remove_index_fail(index: usize, len: usize) -> ! {
+    panic!("removal index (is {}) should be < len (is {})", index, len)
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn drain_order_fail(start: usize, end: usize) -> ! {
+    panic!("slice index starts at {} but ends at {}", start, end)
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn drain_end_fail(end: usize, len: usize) -> ! {
+    panic!(
+        "range end index {} out of range for slice of length {}",
+        end, len
+    )
+}
+
+impl<T> Drop for MallocedVec<T> {
+    #[inline]
+    fn drop(&mut self) {
+        struct DeallocGuard<'a, T: 'a>(&'a mut MallocedVec<T>);
+
+        impl<'a, T> Drop for DeallocGuard<'a, T> {
+            #[inline]
+            fn drop(&mut self) {
+                if self.0.is_allocated() {
+                    unsafe { sys::free(self.0.buf.as_ptr() as *mut c_void) };
+                }
+            }
+        }
+
+        // Deallocates the memory on drop. If dropping the elements panics, the
+        // memory will still be deallocated.
+        let guard = DeallocGuard(self);
+
+        unsafe { ptr::drop_in_place(guard.0.as_mut_slice()) };
+    }
+}
+
+impl<T> Default for MallocedVec<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for MallocedVec<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> DerefMut for MallocedVec<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MallocedVec<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<T> Extend<T> for MallocedVec<T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        #[cfg(feature = "nightly")]
+        crate::nightly::SpecExtend::spec_extend(self, iter.into_iter());
+
+        #[cfg(not(feature = "nightly"))]
+        self.extend_desugared(iter.into_iter());
+    }
+}
+
+impl<'a, T: Copy + 'a> Extend<&'a T> for MallocedVec<T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl<T> FromIterator<T> for MallocedVec<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+/// Collects the elements into a [`MallocedVec`] and then shrinks it to the
+/// exact length.
+///
+/// # Panics
+///
+/// Panics if `malloc` or `realloc` fails, or if `T` is aligned to more than
+/// `malloc` guarantees.
+impl<T> FromIterator<T> for Malloced<[T]> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        MallocedVec::from_iter(iter).into_boxed_slice()
+    }
+}
+
+impl<T> From<MallocedVec<T>> for Malloced<[T]> {
+    #[inline]
+    fn from(vec: MallocedVec<T>) -> Self {
+        vec.into_boxed_slice()
+    }
+}
+
+/// A draining iterator for [`MallocedVec`].
+///
+/// This is created by [`MallocedVec::drain`].
+pub struct Drain<'a, T> {
+    vec: NonNull<MallocedVec<T>>,
+    iter: slice::Iter<'a, T>,
+    tail_start: usize,
+    tail_len: usize,
+}
+
+unsafe impl<T: Send> Send for Drain<'_, T> {}
+unsafe impl<T: Sync> Sync for Drain<'_, T> {}
+
+impl<T> Drain<'_, T> {
+    /// Returns the remaining elements as a slice.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        self.iter.as_slice()
+    }
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.iter.next().map(|ptr| unsafe { ptr::read(ptr) })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back().map(|ptr| unsafe { ptr::read(ptr) })
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl<T> FusedIterator for Drain<'_, T> {}
+
+impl<T: fmt::Debug> fmt::Debug for Drain<'_, T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Drain").field(&self.as_slice()).finish()
+    }
+}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        struct TailGuard<'r, 'a, T>(&'r mut Drain<'a, T>);
+
+        impl<T> Drop for TailGuard<'_, '_, T> {
+            fn drop(&mut self) {
+                let drain = &mut *self.0;
+                unsafe {
+                    let vec = drain.vec.as_mut();
+                    let start = vec.len;
+                    if drain.tail_len > 0 && drain.tail_start != start {
+                        let base = vec.buf.as_ptr();
+                        ptr::copy(base.add(drain.tail_start), base.add(start), drain.tail_len);
+                    }
+                    vec.len = start + drain.tail_len;
+                }
+            }
+        }
+
+        let remaining = mem::replace(&mut self.iter, [].iter());
+
+        // Shifts the tail back even if dropping the remaining elements panics.
+        let _guard = TailGuard(self);
+
+        unsafe { ptr::drop_in_place(remaining.as_slice() as *const [T] as *mut [T]) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DropCounter;
+    use alloc::string::{String, ToString};
+    use core::cell::Cell;
+
+    #[test]
+    fn push_pop() {
+        let mut vec = MallocedVec::new();
+        assert_eq!(vec.pop(), None);
+
+        vec.push(1);
+        vec.push(2);
+        assert_eq!(vec.pop(), Some(2));
+
+        vec.push(3);
+        vec.push(4);
+        assert_eq!(vec.as_slice(), [1, 3, 4]);
+        assert_eq!(vec.len(), 3);
+
+        assert_eq!(vec.pop(), Some(4));
+        assert_eq!(vec.pop(), Some(3));
+        assert_eq!(vec.pop(), Some(1));
+        assert_eq!(vec.pop(), None);
+        assert!(vec.is_empty());
+    }
+
+    #[test]
+    fn capacity_growth() {
+        let mut vec = MallocedVec::new();
+        assert_eq!(vec.capacity(), 0);
+
+        vec.push(0u64);
+        let mut cap = vec.capacity();
+        assert!(cap >= 1);
+
+        for i in 1..100 {
+            vec.push(i);
+            if vec.capacity() != cap {
+                // Growth is geometric.
+                assert!(vec.capacity() >= cap * 2);
+                cap = vec.capacity();
+            }
+            assert!(vec.capacity() >= vec.len());
+        }
+
+        assert_eq!(vec.iter().sum::<u64>(), (0..100).sum());
+    }
+
+    #[test]
+    fn with_capacity() {
+        let vec = MallocedVec::<u32>::with_capacity(10);
+        assert_eq!(vec.capacity(), 10);
+        assert!(vec.is_empty());
+
+        let vec = MallocedVec::<()>::with_capacity(10);
+        assert_eq!(vec.capacity(), usize::MAX);
+    }
+
+    #[test]
+    fn into_boxed_slice() {
+        let mut vec = MallocedVec::with_capacity(10);
+        vec.push("a".to_string());
+        vec.push("b".to_string());
+
+        let slice = vec.into_boxed_slice();
+        assert_eq!(&*slice, ["a", "b"]);
+
+        let empty = MallocedVec::<String>::new().into_boxed_slice();
+        assert!(empty.is_empty());
+
+        let mut zst = MallocedVec::new();
+        zst.push(());
+        zst.push(());
+        assert_eq!(zst.into_boxed_slice().len(), 2);
+    }
+
+    #[test]
+    fn into_boxed_slice_exact() {
+        let mut vec = MallocedVec::with_capacity(100);
+        vec.extend(0..10u32);
+        assert_eq!(vec.capacity(), 100);
+
+        let slice = vec.into_boxed_slice();
+        assert_eq!(&*slice, (0..10).collect::<alloc::vec::Vec<_>>());
+
+        assert_eq!(
+            Malloced::allocation_size(&slice),
+            10 * mem::size_of::<u32>()
+        );
+
+        let slice = MallocedVec::<u32>::new().into_boxed_slice();
+        assert_eq!(slice.len(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn into_boxed_slice_realloc() {
+        use crate::sys::malloc_count;
+
+        let mut vec = MallocedVec::with_capacity(8);
+        vec.extend(0..4u32);
+        let count = malloc_count();
+        let slice = vec.into_boxed_slice();
+        assert_eq!(malloc_count(), count + 1);
+
+        assert_eq!(&*slice, &[0, 1, 2, 3]);
+
+        // The capacity already matches the length, so no `realloc` is needed.
+        let mut vec = MallocedVec::with_capacity(4);
+        vec.extend(0..4u32);
+        let count = malloc_count();
+        let slice = vec.into_boxed_slice();
+        assert_eq!(malloc_count(), count);
+        assert_eq!(&*slice, &[0, 1, 2, 3]);
+
+        // An empty vector shrinks its buffer with a single `realloc`.
+        let vec = MallocedVec::<u32>::with_capacity(8);
+        let count = malloc_count();
+        let slice = vec.into_boxed_slice();
+        assert_eq!(malloc_count(), count + 1);
+        assert!(slice.is_empty());
+    }
+
+    #[test]
+    fn extend() {
+        let mut vec = MallocedVec::new();
+
+        // Exact size hint.
+        vec.extend(0..3);
+        assert_eq!(vec.as_slice(), [0, 1, 2]);
+
+        // Unknown size hint.
+        vec.extend((3..10).filter(|i| i % 2 == 1));
+        assert_eq!(vec.as_slice(), [0, 1, 2, 3, 5, 7, 9]);
+
+        // By reference.
+        vec.extend(&[10, 11]);
+        vec.extend([12, 13].iter());
+        assert_eq!(vec.as_slice(), [0, 1, 2, 3, 5, 7, 9, 10, 11, 12, 13]);
+
+        // From a malloc-ed slice.
+        let buf = Malloced::<[i32]>::alloc(&[14, 15]).unwrap();
+        vec.extend(buf);
+        assert_eq!(vec.len(), 13);
+        assert_eq!(vec[11..], [14, 15]);
+    }
+
+    #[test]
+    fn from_iter() {
+        let vec: MallocedVec<String> = (0..5).map(|i| i.to_string()).collect();
+        assert_eq!(vec.as_slice(), ["0", "1", "2", "3", "4"]);
+
+        let slice: Malloced<[u8]> = b"hello".iter().copied().collect();
+        assert_eq!(&*slice, b"hello");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extend_panic() {
+        let drops = Cell::new(0);
+        let mut vec = MallocedVec::new();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            vec.extend((0..10).map(|i| {
+                if i == 5 {
+                    panic!("extend panic");
+                }
+                DropCounter(&drops)
+            }));
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(vec.len(), 5);
+
+        drop(vec);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn reserve() {
+        let mut vec = MallocedVec::<u32>::new();
+
+        vec.reserve(10);
+        assert!(vec.capacity() >= 10);
+
+        let cap = vec.capacity();
+        vec.reserve(cap);
+        assert_eq!(vec.capacity(), cap);
+
+        vec.extend(0..cap as u32);
+        vec.reserve(1);
+        assert!(vec.capacity() >= cap * 2);
+    }
+
+    #[test]
+    fn try_reserve() {
+        let mut vec = MallocedVec::<u32>::new();
+        vec.try_reserve(10).unwrap();
+        assert!(vec.capacity() >= 10);
+
+        vec.extend(0..5);
+        assert_eq!(vec.try_reserve(usize::MAX), Err(AllocError));
+        assert_eq!(vec.as_slice(), &[0, 1, 2, 3, 4]);
+
+        let mut zst = MallocedVec::<()>::new();
+        zst.push(());
+        assert_eq!(zst.try_reserve(usize::MAX), Err(AllocError));
+        assert_eq!(zst.len(), 1);
+    }
+
+    #[test]
+    fn try_reserve_size_overflow() {
+        let mut vec = MallocedVec::<u32>::new();
+        vec.extend(0..4);
+        let buf = vec.as_slice().as_ptr();
+        let cap = vec.capacity();
+
+        // The length does not overflow, but its size in bytes does.
+        let huge = isize::MAX as usize / 2;
+
+        assert_eq!(vec.try_reserve(huge), Err(AllocError));
+        assert_eq!(vec.as_slice(), &[0, 1, 2, 3]);
+        assert_eq!(vec.as_slice().as_ptr(), buf);
+        assert_eq!(vec.capacity(), cap);
+
+        let mut empty = MallocedVec::<u32>::new();
+        assert_eq!(empty.try_reserve(huge), Err(AllocError));
+        assert_eq!(empty.capacity(), 0);
+
+        vec.try_reserve(cap * 4).unwrap();
+        assert!(vec.capacity() >= cap * 5);
+        assert_eq!(vec.as_slice(), &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn try_reserve_realloc_failure() {
+        use crate::sys::fail_next_alloc;
+
+        let mut vec = MallocedVec::<u32>::new();
+        vec.extend(0..4);
+        let buf = vec.as_slice().as_ptr();
+        let cap = vec.capacity();
+
+        fail_next_alloc();
+        assert_eq!(vec.try_reserve(cap * 4), Err(AllocError));
+        assert_eq!(vec.as_slice(), &[0, 1, 2, 3]);
+        assert_eq!(vec.as_slice().as_ptr(), buf);
+        assert_eq!(vec.capacity(), cap);
+
+        let mut empty = MallocedVec::<u32>::new();
+        fail_next_alloc();
+        assert_eq!(empty.try_reserve(1), Err(AllocError));
+        assert_eq!(empty.capacity(), 0);
+
+        vec.try_reserve(cap * 4).unwrap();
+        assert!(vec.capacity() >= cap * 5);
+        assert_eq!(vec.as_slice(), &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut vec = MallocedVec::with_capacity(100);
+        vec.extend(0..10u64);
+
+        vec.shrink_to_fit();
+        assert_eq!(vec.capacity(), 10);
+        assert_eq!(vec.as_slice(), (0..10).collect::<alloc::vec::Vec<_>>());
+
+        while vec.pop().is_some() {}
+        vec.shrink_to_fit();
+        assert_eq!(vec.capacity(), 0);
+    }
+
+    #[test]
+    fn realloc_preserves_contents() {
+        let mut vec = MallocedVec::new();
+        vec.push("first".to_string());
+
+        // Force several reallocations, which are likely to move the buffer.
+        for i in 0..1000 {
+            vec.reserve(vec.capacity() * 4);
+            vec.push(i.to_string());
+            vec.shrink_to_fit();
+        }
+
+        assert_eq!(vec[0], "first");
+        for (i, s) in vec[1..].iter().enumerate() {
+            assert_eq!(*s, i.to_string());
+        }
+    }
+
+    #[test]
+    fn insert() {
+        let mut vec = MallocedVec::new();
+
+        vec.insert(0, "b".to_string());
+        vec.insert(0, "a".to_string());
+        vec.insert(2, "d".to_string());
+        vec.insert(2, "c".to_string());
+        assert_eq!(vec.as_slice(), ["a", "b", "c", "d"]);
+        assert_eq!(vec.len(), 4);
+
+        // Insert when at capacity.
+        vec.shrink_to_fit();
+        vec.insert(1, "x".to_string());
+        assert_eq!(vec.as_slice(), ["a", "x", "b", "c", "d"]);
+        assert_eq!(vec.len(), 5);
+    }
+
+    #[test]
+    #[should_panic = "insertion index (is 2) should be <= len (is 1)"]
+    fn insert_out_of_bounds() {
+        let mut vec = MallocedVec::new();
+        vec.push(1);
+        vec.insert(2, 2);
+    }
+
+    #[test]
+    fn swap_remove() {
+        let mut vec: MallocedVec<i32> = (0..5).collect();
+
+        assert_eq!(vec.swap_remove(0), 0);
+        assert_eq!(vec.as_slice(), [4, 1, 2, 3]);
+
+        assert_eq!(vec.swap_remove(1), 1);
+        assert_eq!(vec.as_slice(), [4, 3, 2]);
+
+        assert_eq!(vec.swap_remove(2), 2);
+        assert_eq!(vec.as_slice(), [4, 3]);
+    }
+
+    #[test]
+    #[should_panic = "swap_remove index (is 2) should be < len (is 2)"]
+    fn swap_remove_out_of_bounds() {
+        let mut vec: MallocedVec<i32> = (0..2).collect();
+        vec.swap_remove(2);
+    }
+
+    #[test]
+    fn remove() {
+        let mut vec: MallocedVec<String> = (0..5).map(|i| i.to_string()).collect();
+
+        assert_eq!(vec.remove(0), "0");
+        assert_eq!(vec.as_slice(), ["1", "2", "3", "4"]);
+
+        assert_eq!(vec.remove(1), "2");
+        assert_eq!(vec.as_slice(), ["1", "3", "4"]);
+
+        assert_eq!(vec.remove(2), "4");
+        assert_eq!(vec.as_slice(), ["1", "3"]);
+    }
+
+    #[test]
+    #[should_panic = "removal index (is 0) should be < len (is 0)"]
+    fn remove_out_of_bounds() {
+        MallocedVec::<i32>::new().remove(0);
+    }
+
+    #[test]
+    fn drain() {
+        let mut vec: MallocedVec<String> = (0..6).map(|i| i.to_string()).collect();
+
+        let drained: alloc::vec::Vec<String> = vec.drain(1..3).collect();
+        assert_eq!(drained, ["1", "2"]);
+        assert_eq!(vec.as_slice(), ["0", "3", "4", "5"]);
+
+        let mut drain = vec.drain(1..);
+        assert_eq!(drain.next_back().as_deref(), Some("5"));
+        assert_eq!(drain.len(), 2);
+        drop(drain);
+        assert_eq!(vec.as_slice(), ["0"]);
+
+        assert_eq!(vec.drain(..).count(), 1);
+        assert!(vec.is_empty());
+
+        let mut vec: MallocedVec<i32> = (0..5).collect();
+        assert_eq!(vec.drain(2..2).count(), 0);
+        assert_eq!(vec.drain(..=1).collect::<alloc::vec::Vec<_>>(), [0, 1]);
+        assert_eq!(vec.as_slice(), [2, 3, 4]);
+    }
+
+    #[test]
+    fn drain_drops() {
+        let drops = Cell::new(0);
+        let mut vec = MallocedVec::new();
+        for _ in 0..5 {
+            vec.push(DropCounter(&drops));
+        }
+
+        let mut drain = vec.drain(1..4);
+        drop(drain.next());
+        assert_eq!(drops.get(), 1);
+
+        drop(drain);
+        assert_eq!(drops.get(), 3);
+        assert_eq!(vec.len(), 2);
+
+        drop(vec);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn drain_leak() {
+        let mut vec: MallocedVec<String> = (0..5).map(|i| i.to_string()).collect();
+
+        mem::forget(vec.drain(1..3));
+        assert_eq!(vec.as_slice(), ["0"]);
+
+        vec.push("5".to_string());
+        assert_eq!(vec.as_slice(), ["0", "5"]);
+    }
+
+    #[test]
+    #[should_panic = "range end index 3 out of range for slice of length 2"]
+    fn drain_out_of_bounds() {
+        let mut vec: MallocedVec<i32> = (0..2).collect();
+        vec.drain(1..3);
+    }
+
+    #[test]
+    fn drop_elements() {
+        let drops = Cell::new(0);
+
+        let mut vec = MallocedVec::new();
+        for _ in 0..10 {
+            vec.push(DropCounter(&drops));
+        }
+
+        drop(vec.pop());
+        assert_eq!(drops.get(), 1);
+
+        drop(vec);
+        assert_eq!(drops.get(), 10);
+    }
+
+    #[test]
+    fn drop_boxed_slice() {
+        let drops = Cell::new(0);
+
+        let mut vec = MallocedVec::new();
+        for _ in 0..5 {
+            vec.push(DropCounter(&drops));
+        }
+
+        let slice = vec.into_boxed_slice();
+        assert_eq!(drops.get(), 0);
+
+        drop(slice);
+        assert_eq!(drops.get(), 5);
+    }
+}